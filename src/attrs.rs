@@ -0,0 +1,123 @@
+//! Shortcode attribute parsing
+//!
+//! Accepts the WordPress-style attribute forms `key="value"`, `key='value'`,
+//! `key=value` and bare `key` flags. Inside quotes a backslash escapes the
+//! next character. Keys are case-insensitive and stored lowercased.
+
+use std::collections::HashMap;
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// Parse a shortcode attribute string into a key/value map
+///
+/// Never fails: malformed input (stray `=`, unterminated quotes) is parsed
+/// as far as it makes sense and the rest is dropped.
+pub fn parse_shortcode_attrs(input: &str) -> HashMap<String, String> {
+    let mut attrs = HashMap::new();
+    let mut chars = input.chars().peekable();
+
+    loop {
+        skip_whitespace(&mut chars);
+        if chars.peek().is_none() {
+            break;
+        }
+
+        let mut key = String::new();
+        while let Some(c) = chars.next_if(|c| !c.is_whitespace() && *c != '=') {
+            key.push(c);
+        }
+        if key.is_empty() {
+            // A stray `=` with no key in front of it
+            chars.next();
+            continue;
+        }
+
+        skip_whitespace(&mut chars);
+        let value = if chars.next_if_eq(&'=').is_some() {
+            skip_whitespace(&mut chars);
+            match chars.peek().copied() {
+                Some(quote @ ('"' | '\'')) => {
+                    chars.next();
+                    read_quoted(&mut chars, quote)
+                }
+                _ => {
+                    let mut value = String::new();
+                    while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                        value.push(c);
+                    }
+                    value
+                }
+            }
+        } else {
+            String::new()
+        };
+
+        attrs.insert(key.to_lowercase(), value);
+    }
+
+    attrs
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars<'_>>) {
+    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+}
+
+/// Read up to the closing `quote` (already past the opening one)
+fn read_quoted(chars: &mut Peekable<Chars<'_>>, quote: char) -> String {
+    let mut value = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                if let Some(escaped) = chars.next() {
+                    value.push(escaped);
+                }
+            }
+            c if c == quote => break,
+            c => value.push(c),
+        }
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_parse_forms() {
+        let attrs = parse_shortcode_attrs(r#"format="text" Name='Ann Lee' count=3 site"#);
+        assert_eq!(attrs["format"], "text");
+        assert_eq!(attrs["name"], "Ann Lee");
+        assert_eq!(attrs["count"], "3");
+        assert_eq!(attrs["site"], "");
+    }
+
+    #[test]
+    fn test_parse_escapes_and_malformed() {
+        let attrs = parse_shortcode_attrs(r#"say="a \"quoted\" word" = broken="unterminated"#);
+        assert_eq!(attrs["say"], r#"a "quoted" word"#);
+        assert_eq!(attrs["broken"], "unterminated");
+        assert_eq!(attrs.len(), 2);
+    }
+
+    proptest! {
+        #[test]
+        fn prop_arbitrary_bytes_never_panic(bytes in proptest::collection::vec(any::<u8>(), 0..512)) {
+            let input = String::from_utf8_lossy(&bytes);
+            for (key, value) in parse_shortcode_attrs(&input) {
+                prop_assert!(!key.is_empty());
+                prop_assert!(!key.contains(|c: char| c.is_whitespace() || c == '='));
+                prop_assert!(std::str::from_utf8(key.as_bytes()).is_ok());
+                prop_assert!(std::str::from_utf8(value.as_bytes()).is_ok());
+            }
+        }
+
+        #[test]
+        fn prop_quoted_values_round_trip(key in "[a-z][a-z0-9_-]{0,12}", value in "\\PC*") {
+            let escaped = value.replace('\\', "\\\\").replace('"', "\\\"");
+            let attrs = parse_shortcode_attrs(&format!(r#"{}="{}""#, key, escaped));
+            prop_assert_eq!(attrs.get(&key), Some(&value));
+        }
+    }
+}
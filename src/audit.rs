@@ -0,0 +1,99 @@
+//! Audit trail of settings changes
+//!
+//! Every settings update that changes something is recorded with the time,
+//! who made it (when the caller says) and which fields changed. Only the
+//! most recent [`MAX_AUDIT_ENTRIES`] are kept.
+
+use crate::HelloWorldSettings;
+use chrono::{DateTime, Utc};
+use parking_lot::RwLock;
+use serde::Serialize;
+use std::collections::VecDeque;
+
+/// Entries kept before the oldest are dropped
+pub const MAX_AUDIT_ENTRIES: usize = 100;
+
+/// One recorded settings change
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AuditEntry {
+    pub timestamp: DateTime<Utc>,
+    /// Who made the change, if the caller said
+    pub actor: Option<String>,
+    /// Top-level settings fields whose values changed
+    pub changed_fields: Vec<String>,
+}
+
+/// Bounded, oldest-first audit trail
+#[derive(Debug, Default)]
+pub(crate) struct AuditLog(RwLock<VecDeque<AuditEntry>>);
+
+impl AuditLog {
+    /// Record a change of `changed_fields`, from [`changed_fields`]
+    pub fn record(
+        &self,
+        timestamp: DateTime<Utc>,
+        actor: Option<&str>,
+        changed_fields: Vec<String>,
+    ) {
+        let mut entries = self.0.write();
+        if entries.len() == MAX_AUDIT_ENTRIES {
+            entries.pop_front();
+        }
+        entries.push_back(AuditEntry {
+            timestamp,
+            actor: actor.map(str::to_string),
+            changed_fields,
+        });
+    }
+
+    pub fn entries(&self) -> Vec<AuditEntry> {
+        self.0.read().iter().cloned().collect()
+    }
+}
+
+/// Names of the top-level fields that differ between `old` and `new`, in
+/// alphabetical order
+pub(crate) fn changed_fields(old: &HelloWorldSettings, new: &HelloWorldSettings) -> Vec<String> {
+    let (Ok(serde_json::Value::Object(old)), Ok(serde_json::Value::Object(new))) =
+        (serde_json::to_value(old), serde_json::to_value(new))
+    else {
+        return Vec::new();
+    };
+
+    new.iter()
+        .filter(|(field, value)| old.get(*field) != Some(value))
+        .map(|(field, _)| field.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_is_bounded() {
+        let log = AuditLog::default();
+        for i in 0..MAX_AUDIT_ENTRIES + 5 {
+            log.record(Utc::now(), None, vec![format!("field_{}", i)]);
+        }
+
+        let entries = log.entries();
+        assert_eq!(entries.len(), MAX_AUDIT_ENTRIES);
+        assert_eq!(entries[0].changed_fields, ["field_5"]);
+    }
+
+    #[test]
+    fn test_changed_fields() {
+        let old = HelloWorldSettings::default();
+        let new = HelloWorldSettings {
+            greeting_text: "Howdy".to_string(),
+            debug_markers: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            changed_fields(&old, &new),
+            ["debug_markers", "greeting_text"]
+        );
+        assert!(changed_fields(&old, &old).is_empty());
+    }
+}
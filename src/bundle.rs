@@ -0,0 +1,84 @@
+//! Settings export/import for moving configuration between sites
+//!
+//! When the host provides an [`ExportSecret`], exported bundles carry an
+//! HMAC-SHA256 signature over the settings, and imports reject bundles whose
+//! signature is missing or doesn't match.
+
+use crate::context::ExportSecret;
+use hmac::{Hmac, Mac};
+use rustpress_core::context::AppContext;
+use rustpress_core::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Exported plugin settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SettingsBundle {
+    /// Id of the plugin that exported the bundle
+    pub plugin: String,
+    /// Version of the plugin that exported the bundle
+    pub version: String,
+    /// The settings, as stored
+    pub settings: serde_json::Value,
+    /// Hex-encoded HMAC-SHA256 of `settings`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+impl SettingsBundle {
+    /// Create a bundle, signing it if `ctx` holds an export secret
+    pub(crate) fn new(
+        ctx: &AppContext,
+        plugin: String,
+        version: String,
+        settings: serde_json::Value,
+    ) -> Self {
+        let signature =
+            secret(ctx).map(|key| hex::encode(mac(&key, &settings).finalize().into_bytes()));
+        Self {
+            plugin,
+            version,
+            settings,
+            signature,
+        }
+    }
+
+    /// Check the signature, which an export secret in `ctx` makes required
+    pub(crate) fn verify(&self, ctx: &AppContext) -> Result<()> {
+        let key = secret(ctx);
+        let (signature, key) = match (&self.signature, key) {
+            (None, None) => return Ok(()),
+            (None, Some(_)) => {
+                return Err(Error::validation(
+                    "settings bundle is unsigned but an export secret is configured",
+                ))
+            }
+            (Some(_), None) => {
+                return Err(Error::validation(
+                    "settings bundle is signed but no export secret is configured",
+                ))
+            }
+            (Some(signature), Some(key)) => (signature, key),
+        };
+        let signature = hex::decode(signature)
+            .map_err(|_| Error::validation("settings bundle signature is not valid hex"))?;
+
+        mac(&key, &self.settings)
+            .verify_slice(&signature)
+            .map_err(|_| Error::validation("settings bundle signature does not match"))
+    }
+}
+
+fn secret(ctx: &AppContext) -> Option<Vec<u8>> {
+    ctx.get::<ExportSecret>().map(|secret| secret.0.clone())
+}
+
+/// MAC over the canonical JSON of `settings`; `serde_json` maps are sorted,
+/// so equal settings always serialize identically
+fn mac(key: &[u8], settings: &serde_json::Value) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(settings.to_string().as_bytes());
+    mac
+}
@@ -0,0 +1,161 @@
+//! Time source and timezone handling
+//!
+//! Everything date-dependent reads the time through a [`Clock`] so tests and
+//! previews can pin it, and interprets it in the configured timezone.
+//!
+//! [`SystemClock`] needs the `native` feature. Targets without a system clock,
+//! such as WASM, get the time from the host through a [`HostClock`].
+
+use crate::HelloWorldSettings;
+use chrono::{DateTime, Utc};
+use chrono_tz::{Tz, TZ_VARIANTS};
+use rustpress_core::error::{Error, Result};
+use std::sync::Arc;
+
+/// Names people use for UTC that aren't spelled `UTC`
+const UTC_ALIASES: [&str; 7] = ["Z", "Zulu", "GMT", "UCT", "Universal", "Etc/UTC", "Etc/GMT"];
+
+/// Source of the current time
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// The system clock
+#[cfg(feature = "native")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+#[cfg(feature = "native")]
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// A clock stopped at a fixed instant
+#[derive(Debug, Clone, Copy)]
+pub struct FixedClock(pub DateTime<Utc>);
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.0
+    }
+}
+
+/// A clock that asks the host for the time
+pub struct HostClock(Box<dyn Fn() -> DateTime<Utc> + Send + Sync>);
+
+impl HostClock {
+    pub fn new(now: impl Fn() -> DateTime<Utc> + Send + Sync + 'static) -> Self {
+        Self(Box::new(now))
+    }
+}
+
+impl Clock for HostClock {
+    fn now(&self) -> DateTime<Utc> {
+        (self.0)()
+    }
+}
+
+/// The clock a new plugin starts with
+///
+/// Without `native` there is no time source to fall back to, so the clock
+/// reads the Unix epoch until the host supplies one through
+/// [`HelloWorldPlugin::with_clock`](crate::HelloWorldPlugin::with_clock).
+pub(crate) fn default_clock() -> Arc<dyn Clock> {
+    #[cfg(feature = "native")]
+    {
+        Arc::new(SystemClock)
+    }
+    #[cfg(not(feature = "native"))]
+    {
+        Arc::new(FixedClock(DateTime::UNIX_EPOCH))
+    }
+}
+
+/// The canonical IANA name of timezone `input`
+///
+/// Names are matched case-insensitively, so `europe/paris` gives
+/// `Europe/Paris`, and the common spellings of UTC, such as `GMT` and `Z`,
+/// give `UTC`.
+pub fn normalize_timezone(input: &str) -> Result<String> {
+    let input = input.trim();
+    if UTC_ALIASES
+        .iter()
+        .any(|alias| alias.eq_ignore_ascii_case(input))
+    {
+        return Ok(Tz::UTC.name().to_string());
+    }
+
+    TZ_VARIANTS
+        .iter()
+        .find(|tz| tz.name().eq_ignore_ascii_case(input))
+        .map(|tz| tz.name().to_string())
+        .ok_or_else(|| Error::validation(format!("{:?} is not a known IANA timezone", input)))
+}
+
+/// Timezone `name`, spelled any way [`normalize_timezone`] accepts
+///
+/// This runs on every render, so a name that's already canonical is parsed
+/// directly; only other spellings go through the slower lookup.
+pub(crate) fn parse_timezone(name: &str) -> Option<Tz> {
+    name.parse()
+        .ok()
+        .or_else(|| normalize_timezone(name).ok()?.parse().ok())
+}
+
+/// The configured timezone, UTC if it isn't known
+pub(crate) fn timezone(settings: &HelloWorldSettings) -> Tz {
+    parse_timezone(&settings.timezone).unwrap_or(Tz::UTC)
+}
+
+/// `now` in the configured timezone
+pub(crate) fn local_time(settings: &HelloWorldSettings, now: DateTime<Utc>) -> DateTime<Tz> {
+    now.with_timezone(&timezone(settings))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_timezone() {
+        assert_eq!(normalize_timezone("GMT").unwrap(), "UTC");
+        assert_eq!(normalize_timezone("z").unwrap(), "UTC");
+        assert_eq!(
+            normalize_timezone(" europe/paris ").unwrap(),
+            "Europe/Paris"
+        );
+        assert_eq!(
+            normalize_timezone("America/New_York").unwrap(),
+            "America/New_York"
+        );
+    }
+
+    #[test]
+    fn test_parse_timezone() {
+        assert_eq!(parse_timezone("Europe/Paris"), Some(Tz::Europe__Paris));
+        assert_eq!(parse_timezone(" europe/paris "), Some(Tz::Europe__Paris));
+        assert_eq!(parse_timezone("z"), Some(Tz::UTC));
+        assert_eq!(parse_timezone("Mars/Olympus_Mons"), None);
+    }
+
+    #[test]
+    fn test_unknown_timezone_rejected() {
+        assert!(normalize_timezone("Mars/Olympus_Mons").is_err());
+        assert!(normalize_timezone("").is_err());
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod wasm_tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_host_clock_returns_injected_time() {
+        let injected = Utc.with_ymd_and_hms(2024, 1, 5, 12, 0, 0).unwrap();
+        let clock = HostClock::new(move || injected);
+        assert_eq!(clock.now(), injected);
+    }
+}
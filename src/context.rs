@@ -0,0 +1,91 @@
+//! Values the plugin reads from the host's [`AppContext`]
+//!
+//! The context is keyed by type, so each value gets its own newtype. All of
+//! them are optional: when the host doesn't provide one the plugin falls back
+//! to an empty value.
+//!
+//! [`AppContext`]: rustpress_core::context::AppContext
+
+use parking_lot::Mutex;
+use rustpress_core::context::AppContext;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Display name of the site
+#[derive(Debug, Clone)]
+pub struct SiteName(pub String);
+
+/// Stable identifier of the site, such as its domain
+///
+/// Seeds the daily greeting rotation, so sites sharing a greeting list don't
+/// all show the same greeting on the same day.
+#[derive(Debug, Clone)]
+pub struct SiteId(pub String);
+
+/// Display name of the user the page is rendered for
+#[derive(Debug, Clone)]
+pub struct CurrentUser(pub String);
+
+/// Whether the visitor has been here before, e.g. from a cookie the host
+/// sets on the first visit
+///
+/// New visitors see `first_visit_greeting` if one is configured. Without
+/// this value every visitor is greeted the usual way.
+#[derive(Debug, Clone, Copy)]
+pub struct ReturningVisitor(pub bool);
+
+/// `User-Agent` header of the request, used by `skip_for_bots` to spot
+/// crawlers
+#[derive(Debug, Clone)]
+pub struct UserAgent(pub String);
+
+/// Metadata of the post being rendered, such as its `title`
+///
+/// Each entry is available to greeting templates as `post_<key>`.
+#[derive(Debug, Clone, Default)]
+pub struct PostMeta(pub HashMap<String, String>);
+
+/// Key for signing and verifying exported settings bundles
+#[derive(Clone)]
+pub struct ExportSecret(pub Vec<u8>);
+
+/// Per-request flags, stored in the context as `Arc<RequestState>`
+///
+/// The host installs a fresh one for every page render, so shortcodes can
+/// leave flags for filters that run later in the same request.
+#[derive(Debug, Default)]
+pub struct RequestState {
+    no_footer: AtomicBool,
+    /// Fingerprints of the stylesheets emitted into this request's head
+    head_styles: Mutex<HashSet<u64>>,
+}
+
+impl RequestState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Skip the content footer for the rest of this request
+    pub fn suppress_footer(&self) {
+        self.no_footer.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether `[hello_no_footer]` appeared on this request's page
+    pub fn footer_suppressed(&self) -> bool {
+        self.no_footer.load(Ordering::Relaxed)
+    }
+
+    /// Note that the stylesheet with `fingerprint` went into the head,
+    /// returning whether it's the first time this request
+    pub(crate) fn claim_head_style(&self, fingerprint: u64) -> bool {
+        self.head_styles.lock().insert(fingerprint)
+    }
+}
+
+/// Whether the current request opted out of the content footer
+pub(crate) fn footer_suppressed(ctx: &AppContext) -> bool {
+    ctx.get::<Arc<RequestState>>()
+        .map(|request| request.footer_suppressed())
+        .unwrap_or(false)
+}
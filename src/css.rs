@@ -0,0 +1,566 @@
+//! Stylesheet emitted into `wp_head`
+
+use crate::context::RequestState;
+use crate::logging::plugin_log;
+use crate::HelloWorldSettings;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// Default gradient start color
+pub const DEFAULT_GRADIENT_START: &str = "#667eea";
+
+/// Default gradient end color
+pub const DEFAULT_GRADIENT_END: &str = "#764ba2";
+
+/// Gradients `random_gradient` picks from, as `(start, end)`
+pub(crate) const GRADIENT_PALETTE: [(&str, &str); 6] = [
+    (DEFAULT_GRADIENT_START, DEFAULT_GRADIENT_END),
+    ("#f093fb", "#f5576c"),
+    ("#4facfe", "#00f2fe"),
+    ("#43e97b", "#38f9d7"),
+    ("#fa709a", "#fee140"),
+    ("#30cfd0", "#330867"),
+];
+
+/// Blocks of the default stylesheet `css_overrides` can replace, styling
+/// the `hello-world-<component>` class
+pub const CSS_COMPONENTS: [&str; 4] = ["greeting", "date", "widget", "footer"];
+
+/// Default declarations of the date line
+pub const DEFAULT_DATE_CSS: &str = "text-align: center; color: #666; font-style: italic;";
+
+/// Default declarations of the widget
+pub const DEFAULT_WIDGET_CSS: &str = "background: #f5f5f5; padding: 15px; border-radius: 4px;";
+
+/// Contrast ratio below which `enforce_contrast` darkens the greeting text,
+/// the WCAG AA minimum for normal text
+pub const MIN_CONTRAST_RATIO: f64 = 4.5;
+
+/// Greeting text color when white lacks contrast against the gradient
+const DARK_TEXT_COLOR: &str = "#1a1a1a";
+
+/// Inline declarations of the footer, which has no block in the default
+/// stylesheet
+pub(crate) const FOOTER_INLINE_STYLE: &str =
+    "font-size: 0.8em; color: #999; margin-top: 20px; padding-top: 10px; border-top: 1px solid #eee;";
+
+/// Indentation of the blocks in the emitted stylesheet
+const BLOCK_INDENT: &str = "                ";
+
+/// How the plugin's markup gets its styles
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StylingMode {
+    /// A `<style>` block in `wp_head`
+    #[default]
+    HeadCss,
+    /// `style` attributes on the elements, for sites that forbid `<style>`
+    InlineStyle,
+    /// No styles at all; the theme is expected to style the classes
+    None,
+}
+
+/// Inline declarations for the greeting, mirroring the default stylesheet
+pub(crate) fn greeting_inline_style(settings: &HelloWorldSettings) -> String {
+    greeting_css(
+        &settings.gradient_start,
+        &settings.gradient_end,
+        settings.enforce_contrast,
+    )
+}
+
+/// Default declarations of the greeting with the given gradient
+fn greeting_css(gradient_start: &str, gradient_end: &str, enforce_contrast: bool) -> String {
+    format!(
+        "padding: 20px; background: linear-gradient(135deg, {} 0%, {} 100%); color: {}; \
+         border-radius: 8px; margin: 20px 0; font-size: 1.5em; text-align: center;",
+        gradient_start,
+        gradient_end,
+        text_color(gradient_start, gradient_end, enforce_contrast)
+    )
+}
+
+/// White, or with `enforce_contrast` a dark color if white doesn't reach
+/// [`MIN_CONTRAST_RATIO`] against both ends of the gradient
+fn text_color(gradient_start: &str, gradient_end: &str, enforce_contrast: bool) -> &'static str {
+    let white_lacks_contrast = [gradient_start, gradient_end]
+        .into_iter()
+        .filter_map(relative_luminance)
+        .any(|luminance| contrast_ratio(1.0, luminance) < MIN_CONTRAST_RATIO);
+    if enforce_contrast && white_lacks_contrast {
+        DARK_TEXT_COLOR
+    } else {
+        "white"
+    }
+}
+
+/// WCAG contrast ratio between two relative luminances
+fn contrast_ratio(a: f64, b: f64) -> f64 {
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+/// WCAG relative luminance of a `#rgb` or `#rrggbb` color
+fn relative_luminance(color: &str) -> Option<f64> {
+    if !is_hex_color(color) {
+        return None;
+    }
+    let hex = &color[1..];
+    let channel = |i: usize| -> f64 {
+        let value = if hex.len() == 3 {
+            // `#abc` is `#aabbcc`
+            u8::from_str_radix(&hex[i..i + 1], 16).unwrap_or(0) * 17
+        } else {
+            u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).unwrap_or(0)
+        };
+        let srgb = f64::from(value) / 255.0;
+        if srgb <= 0.03928 {
+            srgb / 12.92
+        } else {
+            ((srgb + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    Some(0.2126 * channel(0) + 0.7152 * channel(1) + 0.0722 * channel(2))
+}
+
+/// Inline declarations for the date line, mirroring the default stylesheet
+pub(crate) const DATE_INLINE_STYLE: &str = DEFAULT_DATE_CSS;
+
+/// Smaller greeting on narrow screens, appended when `responsive` is on
+const RESPONSIVE_CSS: &str = r#"
+                @media (max-width: 600px) {
+                    .hello-world-greeting {
+                        padding: 12px;
+                        font-size: 1.2em;
+                    }
+                }
+                "#;
+
+/// The CSS to emit for `settings`: the custom CSS if any, otherwise the
+/// default styles with the configured gradient, followed by the mobile
+/// overrides when `responsive` is on
+///
+/// Only a custom gradient or `responsive` allocates; the stock stylesheet is
+/// built once and custom CSS is borrowed from the settings.
+pub(crate) fn head_css(settings: &HelloWorldSettings) -> Cow<'_, str> {
+    let base = base_css(settings);
+    if settings.responsive {
+        Cow::Owned(base.into_owned() + RESPONSIVE_CSS)
+    } else {
+        base
+    }
+}
+
+/// [`head_css`], except that with `random_gradient` on and no custom CSS
+/// the gradient is the [`GRADIENT_PALETTE`] entry `seed` picks
+pub(crate) fn head_css_seeded(settings: &HelloWorldSettings, seed: u64) -> Cow<'_, str> {
+    if !settings.random_gradient || !settings.custom_css.is_empty() {
+        return head_css(settings);
+    }
+    let (start, end) = GRADIENT_PALETTE[(seed % GRADIENT_PALETTE.len() as u64) as usize];
+    let mut css = stylesheet(start, end, settings);
+    if settings.responsive {
+        css.push_str(RESPONSIVE_CSS);
+    }
+    Cow::Owned(css)
+}
+
+/// Whether `wp_head` emits styles for `settings`: with head CSS styling, or
+/// with inline styling in `amp_mode`, which moves the inline styles there
+pub(crate) fn styles_in_head(settings: &HelloWorldSettings) -> bool {
+    match settings.styling_mode {
+        StylingMode::HeadCss => true,
+        StylingMode::InlineStyle => settings.amp_mode,
+        StylingMode::None => false,
+    }
+}
+
+/// Everything `amp_mode` styles with: [`head_css_seeded`], plus a block for
+/// the footer's otherwise inline styles when the footer is shown and not
+/// already styled by `css_overrides`
+pub(crate) fn amp_css(settings: &HelloWorldSettings, seed: u64) -> String {
+    let mut css = head_css_seeded(settings, seed).into_owned();
+    if settings.append_footer && !settings.css_overrides.contains_key("footer") {
+        css.push_str(&format!(
+            ".hello-world-footer {{ {} }}",
+            FOOTER_INLINE_STYLE
+        ));
+    }
+    css
+}
+
+/// The `<style>` block `wp_head` emits, or `None` when `dedupe_head_css` is
+/// on and another instance already put the same styles into `request`
+///
+/// In `amp_mode` it's a `<style amp-custom>` block of [`amp_css`]. Styles
+/// over `max_inline_css_bytes` are linked from `stylesheet_url` instead, or
+/// cut short when there is none or in `amp_mode`, where AMP forbids
+/// stylesheet links.
+pub(crate) fn head_style(
+    settings: &HelloWorldSettings,
+    request: Option<&RequestState>,
+    seed: u64,
+) -> Option<String> {
+    let css = if settings.amp_mode {
+        Cow::Owned(amp_css(settings, seed))
+    } else {
+        head_css_seeded(settings, seed)
+    };
+    if settings.dedupe_head_css {
+        if let Some(request) = request {
+            if !request.claim_head_style(crate::fnv1a(css.as_bytes())) {
+                return None;
+            }
+        }
+    }
+    let css = match settings.max_inline_css_bytes {
+        Some(max) if css.len() > max => match &settings.stylesheet_url {
+            // Validation guarantees the URL needs no attribute escaping
+            Some(url) if !settings.amp_mode => {
+                return Some(format!(r#"<link rel="stylesheet" href="{}">"#, url));
+            }
+            _ => {
+                let truncated = truncate_css(&css, max);
+                plugin_log!(
+                    settings.log_config(),
+                    WARN,
+                    event = "inline_css_truncated",
+                    bytes = css.len(),
+                    max = max;
+                    "head CSS is {} bytes, over max_inline_css_bytes, cutting it to {}",
+                    css.len(),
+                    truncated.len()
+                );
+                Cow::Owned(truncated.to_string())
+            }
+        },
+        _ => css,
+    };
+
+    let element = if settings.amp_mode {
+        "style amp-custom"
+    } else {
+        "style"
+    };
+    Some(format!("<{}>{}</style>", element, css))
+}
+
+/// The longest run of whole rules at the start of `css` that fits in `max`
+/// bytes
+///
+/// Only a brace closing a top-level block ends a rule, so an `@media` block
+/// is kept whole or dropped with everything after it.
+fn truncate_css(css: &str, max: usize) -> &str {
+    let mut depth = 0usize;
+    let mut end = 0;
+    for (i, byte) in css.bytes().enumerate().take(max) {
+        match byte {
+            b'{' => depth += 1,
+            b'}' => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    end = i + 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    &css[..end]
+}
+
+fn base_css(settings: &HelloWorldSettings) -> Cow<'_, str> {
+    if !settings.custom_css.is_empty() {
+        Cow::Borrowed(&settings.custom_css)
+    } else if settings.gradient_start == DEFAULT_GRADIENT_START
+        && settings.gradient_end == DEFAULT_GRADIENT_END
+        && settings.css_overrides.is_empty()
+        && !settings.enforce_contrast
+    {
+        Cow::Borrowed(stock_css())
+    } else {
+        Cow::Owned(stylesheet(
+            &settings.gradient_start,
+            &settings.gradient_end,
+            settings,
+        ))
+    }
+}
+
+/// The default stylesheet with the default gradient
+fn stock_css() -> &'static str {
+    static STOCK_CSS: OnceLock<String> = OnceLock::new();
+    STOCK_CSS.get_or_init(|| default_css(DEFAULT_GRADIENT_START, DEFAULT_GRADIENT_END))
+}
+
+fn default_css(gradient_start: &str, gradient_end: &str) -> String {
+    let greeting = greeting_css(gradient_start, gradient_end, false);
+    components_css(&greeting, &HashMap::new())
+}
+
+/// The default stylesheet with the given gradient, styled by `settings`'
+/// `css_overrides` and `enforce_contrast`
+fn stylesheet(gradient_start: &str, gradient_end: &str, settings: &HelloWorldSettings) -> String {
+    let greeting = greeting_css(gradient_start, gradient_end, settings.enforce_contrast);
+    components_css(&greeting, &settings.css_overrides)
+}
+
+/// The component blocks, with the declarations of each component in
+/// `overrides` replacing the defaults
+///
+/// The footer is styled inline and has no block unless overridden; a
+/// component overridden with nothing loses its block.
+fn components_css(greeting: &str, overrides: &HashMap<String, String>) -> String {
+    let defaults = [
+        ("greeting", greeting),
+        ("date", DEFAULT_DATE_CSS),
+        ("widget", DEFAULT_WIDGET_CSS),
+        ("footer", ""),
+    ];
+
+    let mut css = String::from("\n");
+    for (component, default) in defaults {
+        let declarations = overrides.get(component).map_or(default, String::as_str);
+        let mut declarations = declarations
+            .split(';')
+            .map(str::trim)
+            .filter(|declaration| !declaration.is_empty())
+            .peekable();
+        if declarations.peek().is_none() {
+            continue;
+        }
+        css.push_str(&format!("{}.hello-world-{} {{\n", BLOCK_INDENT, component));
+        for declaration in declarations {
+            css.push_str(&format!("{}    {};\n", BLOCK_INDENT, declaration));
+        }
+        css.push_str(BLOCK_INDENT);
+        css.push_str("}\n");
+    }
+    css.push_str(BLOCK_INDENT);
+    css
+}
+
+/// Whether `declarations` can go inside a CSS block without ending it or
+/// the `<style>` element
+pub(crate) fn is_safe_declarations(declarations: &str) -> bool {
+    !declarations.contains(['{', '}', '<', '>'])
+}
+
+/// Whether `value` is a `#rgb` or `#rrggbb` hex color
+pub(crate) fn is_hex_color(value: &str) -> bool {
+    match value.strip_prefix('#') {
+        Some(hex) => matches!(hex.len(), 3 | 6) && hex.chars().all(|c| c.is_ascii_hexdigit()),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stock_css_matches_original() {
+        let original = r#"
+                .hello-world-greeting {
+                    padding: 20px;
+                    background: linear-gradient(135deg, #667eea 0%, #764ba2 100%);
+                    color: white;
+                    border-radius: 8px;
+                    margin: 20px 0;
+                    font-size: 1.5em;
+                    text-align: center;
+                }
+                .hello-world-date {
+                    text-align: center;
+                    color: #666;
+                    font-style: italic;
+                }
+                .hello-world-widget {
+                    background: #f5f5f5;
+                    padding: 15px;
+                    border-radius: 4px;
+                }
+                "#;
+        assert_eq!(stock_css(), original);
+
+        // Default settings reuse the precomputed stylesheet
+        let settings = HelloWorldSettings::default();
+        for _ in 0..1000 {
+            let css = head_css(&settings);
+            assert!(matches!(css, Cow::Borrowed(_)));
+            assert!(std::ptr::eq(css.as_ref(), stock_css()));
+        }
+    }
+
+    #[test]
+    fn test_responsive_media_query() {
+        let settings = HelloWorldSettings::default();
+        assert!(!head_css(&settings).contains("@media"));
+
+        let settings = HelloWorldSettings {
+            responsive: true,
+            ..Default::default()
+        };
+        let css = head_css(&settings);
+        assert!(css.starts_with(stock_css()));
+        assert!(css.contains("@media (max-width: 600px)"));
+    }
+
+    #[test]
+    fn test_random_gradient_is_seeded() {
+        let settings = HelloWorldSettings {
+            random_gradient: true,
+            ..Default::default()
+        };
+        for (seed, (start, end)) in [(2, GRADIENT_PALETTE[2]), (9, GRADIENT_PALETTE[3])] {
+            let css = head_css_seeded(&settings, seed);
+            let gradient = format!("linear-gradient(135deg, {} 0%, {} 100%)", start, end);
+            assert!(css.contains(&gradient), "{}", css);
+            assert_eq!(css, head_css_seeded(&settings, seed));
+        }
+
+        // Off, the seed is ignored
+        let settings = HelloWorldSettings::default();
+        assert!(std::ptr::eq(
+            head_css_seeded(&settings, 2).as_ref(),
+            stock_css()
+        ));
+    }
+
+    #[test]
+    fn test_head_style_deduped_per_request() {
+        let first = HelloWorldSettings::default();
+        let second = HelloWorldSettings::default();
+        let request = RequestState::new();
+        assert!(head_style(&first, Some(&request), 0).is_some());
+        assert_eq!(head_style(&second, Some(&request), 0), None);
+
+        // Different styles, or a new request, are emitted
+        let custom = HelloWorldSettings {
+            custom_css: ".hello-world-greeting { color: red; }".to_string(),
+            ..Default::default()
+        };
+        assert!(head_style(&custom, Some(&request), 0).is_some());
+        assert!(head_style(&second, Some(&RequestState::new()), 0).is_some());
+
+        let duplicate = HelloWorldSettings {
+            dedupe_head_css: false,
+            ..Default::default()
+        };
+        assert!(head_style(&duplicate, Some(&request), 0).is_some());
+    }
+
+    #[test]
+    fn test_css_override_replaces_one_block() {
+        let settings = HelloWorldSettings {
+            css_overrides: HashMap::from([(
+                "date".to_string(),
+                "color: #c00; font-weight: bold".to_string(),
+            )]),
+            ..Default::default()
+        };
+        let css = head_css(&settings);
+
+        let date = "                .hello-world-date {\n                    color: #c00;\n                    font-weight: bold;\n                }\n";
+        assert!(css.contains(date), "{}", css);
+        assert!(!css.contains("font-style: italic"));
+        // Everything but the date block is the stock stylesheet
+        let stock = stock_css();
+        let date_start = stock.find("                .hello-world-date").unwrap();
+        let date_end = stock.find("                .hello-world-widget").unwrap();
+        assert!(css.starts_with(&stock[..date_start]));
+        assert!(css.ends_with(&stock[date_end..]));
+    }
+
+    #[test]
+    fn test_enforce_contrast_darkens_text_on_light_gradients() {
+        let mut settings = HelloWorldSettings {
+            gradient_start: "#fdfbfb".to_string(),
+            gradient_end: "#ebedee".to_string(),
+            ..Default::default()
+        };
+        assert!(head_css(&settings).contains("color: white;"));
+
+        settings.enforce_contrast = true;
+        let css = head_css(&settings);
+        assert!(css.contains("color: #1a1a1a;"), "{}", css);
+        assert!(!css.contains("color: white;"));
+        assert!(greeting_inline_style(&settings).contains("color: #1a1a1a;"));
+
+        // White on a dark gradient is fine either way
+        settings.gradient_start = "#1e3c72".to_string();
+        settings.gradient_end = "#2a5298".to_string();
+        assert!(head_css(&settings).contains("color: white;"));
+    }
+
+    #[test]
+    fn test_amp_css_combines_all_styles() {
+        let settings = HelloWorldSettings {
+            amp_mode: true,
+            styling_mode: StylingMode::InlineStyle,
+            ..Default::default()
+        };
+        assert!(styles_in_head(&settings));
+
+        let style = head_style(&settings, None, 0).unwrap();
+        assert!(style.starts_with("<style amp-custom>"));
+        assert_eq!(style.matches("<style").count(), 1);
+        for block in [
+            ".hello-world-greeting {",
+            ".hello-world-date {",
+            ".hello-world-footer {",
+        ] {
+            assert!(style.contains(block), "{} missing from {}", block, style);
+        }
+    }
+
+    #[test]
+    fn test_css_over_budget_is_linked() {
+        let full = head_css(&HelloWorldSettings::default()).len();
+        let mut settings = HelloWorldSettings {
+            max_inline_css_bytes: Some(full - 1),
+            stylesheet_url: Some("https://example.com/hello-world.css".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            head_style(&settings, None, 0).unwrap(),
+            r#"<link rel="stylesheet" href="https://example.com/hello-world.css">"#
+        );
+
+        // Without a URL, whole rules are kept up to the budget
+        settings.stylesheet_url = None;
+        let style = head_style(&settings, None, 0).unwrap();
+        let css = style
+            .strip_prefix("<style>")
+            .unwrap()
+            .strip_suffix("</style>")
+            .unwrap();
+        assert!(css.len() < full);
+        assert!(css.ends_with('}'));
+
+        // Within budget, the styles are inlined as usual
+        settings.max_inline_css_bytes = Some(full);
+        assert!(head_style(&settings, None, 0)
+            .unwrap()
+            .starts_with("<style>"));
+    }
+
+    #[test]
+    fn test_truncate_css_keeps_blocks_whole() {
+        let css = "a { color: red; }\n@media (max-width: 600px) { a { color: blue; } }\nb { }";
+        let media_end = css.find("} }").unwrap() + 3;
+        assert_eq!(truncate_css(css, media_end - 1), "a { color: red; }");
+        assert_eq!(truncate_css(css, media_end), &css[..media_end]);
+        assert_eq!(truncate_css(css, 5), "");
+    }
+
+    #[test]
+    fn test_is_hex_color() {
+        assert!(is_hex_color("#fff"));
+        assert!(is_hex_color("#667eea"));
+        assert!(!is_hex_color("667eea"));
+        assert!(!is_hex_color("#66ee"));
+        assert!(!is_hex_color("#ggg"));
+        assert!(!is_hex_color("red;}</style>"));
+    }
+}
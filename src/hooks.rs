@@ -0,0 +1,273 @@
+//! Hook registration
+//!
+//! The plugin registers its handlers through [`HookSink`] rather than on the
+//! [`HookRegistry`] directly, so wrapping the sink can change how every
+//! handler is registered. Deactivating removes the plugin's own hooks the
+//! last activation recorded through [`HookSink::remove_own_hook`].
+//!
+//! Wrapping a sink in [`Gated`] makes every handler registered through it
+//! conditional, which is how the active date window is enforced. Wrapping
+//! it in [`Recording`] keeps one failed registration from stopping the
+//! rest, collecting the outcome in a [`RegistrationReport`]. Wrapping it in
+//! [`Namespaced`] keeps copies of the plugin loaded under different ids
+//! from registering the same hooks.
+
+use parking_lot::Mutex;
+#[cfg(test)]
+use rustpress_core::error::Error;
+use rustpress_core::error::Result;
+use rustpress_core::hook::HookRegistry;
+use std::sync::Arc;
+
+/// A filter handler
+pub(crate) type FilterFn = Box<dyn Fn(String) -> String + Send + Sync>;
+
+/// An action handler
+pub(crate) type ActionFn = Box<dyn Fn() + Send + Sync>;
+
+/// Somewhere the plugin can register hook handlers
+pub(crate) trait HookSink {
+    /// Register `callback` on filter `name`
+    fn add_filter(&self, name: &str, callback: FilterFn, priority: i32) -> Result<()>;
+
+    /// Register `callback` on action `name`
+    fn add_action(&self, name: &str, callback: ActionFn, priority: i32) -> Result<()>;
+
+    /// Remove the handlers on hook `name` if it's one of the plugin's own,
+    /// as [`is_own_hook`] says
+    ///
+    /// Shared hooks such as `the_content` are left alone: the registry can
+    /// only drop every handler on a name, other plugins' included.
+    fn remove_own_hook(&self, name: &str);
+}
+
+impl HookSink for HookRegistry {
+    fn add_filter(&self, name: &str, callback: FilterFn, priority: i32) -> Result<()> {
+        HookRegistry::add_filter(self, name, move |value: String| callback(value), priority)
+    }
+
+    fn add_action(&self, name: &str, callback: ActionFn, priority: i32) -> Result<()> {
+        HookRegistry::add_action(self, name, move || callback(), priority)
+    }
+
+    fn remove_own_hook(&self, name: &str) {
+        if is_own_hook(name) {
+            HookRegistry::remove_filter(self, name);
+            HookRegistry::remove_action(self, name);
+        }
+    }
+}
+
+/// A sink whose handlers only run while `open` says so
+///
+/// While closed, shortcode and widget filters render nothing, other filters
+/// pass their value through unchanged and actions are skipped.
+pub(crate) struct Gated<'a, S> {
+    sink: &'a S,
+    open: Arc<dyn Fn() -> bool + Send + Sync>,
+}
+
+impl<'a, S: HookSink> Gated<'a, S> {
+    pub fn new(sink: &'a S, open: impl Fn() -> bool + Send + Sync + 'static) -> Self {
+        Self {
+            sink,
+            open: Arc::new(open),
+        }
+    }
+}
+
+impl<S: HookSink> HookSink for Gated<'_, S> {
+    fn add_filter(&self, name: &str, callback: FilterFn, priority: i32) -> Result<()> {
+        let open = Arc::clone(&self.open);
+        let renders = renders_markup(name);
+        let gated = move |value: String| {
+            if open() {
+                callback(value)
+            } else if renders {
+                String::new()
+            } else {
+                value
+            }
+        };
+        self.sink.add_filter(name, Box::new(gated), priority)
+    }
+
+    fn add_action(&self, name: &str, callback: ActionFn, priority: i32) -> Result<()> {
+        let open = Arc::clone(&self.open);
+        let gated = move || {
+            if open() {
+                callback()
+            }
+        };
+        self.sink.add_action(name, Box::new(gated), priority)
+    }
+
+    fn remove_own_hook(&self, name: &str) {
+        self.sink.remove_own_hook(name);
+    }
+}
+
+/// Whether filter `name` produces markup of its own rather than
+/// transforming its value, like shortcodes, whose value is their attributes
+fn renders_markup(name: &str) -> bool {
+    name.starts_with("shortcode_") || name.starts_with("widget_")
+}
+
+/// A sink that suffixes the names of the plugin's own hooks, leaving core
+/// hooks alone
+pub(crate) struct Namespaced<'a, S> {
+    sink: &'a S,
+    suffix: Option<String>,
+}
+
+impl<'a, S: HookSink> Namespaced<'a, S> {
+    pub fn new(sink: &'a S, suffix: Option<String>) -> Self {
+        Self { sink, suffix }
+    }
+
+    fn name(&self, name: &str) -> String {
+        namespaced(name, self.suffix.as_deref())
+    }
+}
+
+impl<S: HookSink> HookSink for Namespaced<'_, S> {
+    fn add_filter(&self, name: &str, callback: FilterFn, priority: i32) -> Result<()> {
+        self.sink.add_filter(&self.name(name), callback, priority)
+    }
+
+    fn add_action(&self, name: &str, callback: ActionFn, priority: i32) -> Result<()> {
+        self.sink.add_action(&self.name(name), callback, priority)
+    }
+
+    fn remove_own_hook(&self, name: &str) {
+        self.sink.remove_own_hook(&self.name(name));
+    }
+}
+
+/// Whether hook `name` is one of the plugin's own: a shortcode, a widget or
+/// a `hello_world_` action, rather than a core hook shared with others
+pub(crate) fn is_own_hook(name: &str) -> bool {
+    renders_markup(name) || name.starts_with("hello_world_")
+}
+
+/// `name` with `_<suffix>` appended if it's one of the plugin's own hooks
+pub(crate) fn namespaced(name: &str, suffix: Option<&str>) -> String {
+    match suffix {
+        Some(suffix) if is_own_hook(name) => {
+            format!("{}_{}", name, suffix)
+        }
+        _ => name.to_string(),
+    }
+}
+
+/// Which hooks the last activation registered handlers on
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RegistrationReport {
+    /// Hooks registered, in registration order
+    pub registered: Vec<String>,
+    /// Hooks that failed to register, with why
+    pub failed: Vec<(String, String)>,
+}
+
+impl RegistrationReport {
+    /// Whether every handler registered
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+/// A sink that reports failed registrations instead of returning them, so
+/// the registrations after a failed one are still attempted
+pub(crate) struct Recording<'a, S> {
+    sink: &'a S,
+    report: Mutex<RegistrationReport>,
+}
+
+impl<'a, S: HookSink> Recording<'a, S> {
+    pub fn new(sink: &'a S) -> Self {
+        Self {
+            sink,
+            report: Mutex::default(),
+        }
+    }
+
+    /// What was registered and what wasn't
+    pub fn into_report(self) -> RegistrationReport {
+        self.report.into_inner()
+    }
+
+    fn record(&self, name: &str, result: Result<()>) {
+        let mut report = self.report.lock();
+        match result {
+            Ok(()) => report.registered.push(name.to_string()),
+            Err(e) => report.failed.push((name.to_string(), e.to_string())),
+        }
+    }
+}
+
+impl<S: HookSink> HookSink for Recording<'_, S> {
+    fn add_filter(&self, name: &str, callback: FilterFn, priority: i32) -> Result<()> {
+        self.record(name, self.sink.add_filter(name, callback, priority));
+        Ok(())
+    }
+
+    fn add_action(&self, name: &str, callback: ActionFn, priority: i32) -> Result<()> {
+        self.record(name, self.sink.add_action(name, callback, priority));
+        Ok(())
+    }
+
+    fn remove_own_hook(&self, name: &str) {
+        self.sink.remove_own_hook(name);
+        self.report.lock().registered.retain(|hook| hook != name);
+    }
+}
+
+/// Put in the context to have `activate` refuse the hooks it matches, as a
+/// registry rejecting a conflicting name would
+#[cfg(test)]
+#[derive(Clone, Copy)]
+pub(crate) struct RefusedHooks(pub fn(&str) -> bool);
+
+/// A sink that refuses the hooks [`RefusedHooks`] matches
+#[cfg(test)]
+pub(crate) struct Refusing<'a, S> {
+    sink: &'a S,
+    refused: Option<RefusedHooks>,
+}
+
+#[cfg(test)]
+impl<'a, S: HookSink> Refusing<'a, S> {
+    pub fn new(sink: &'a S, refused: Option<&RefusedHooks>) -> Self {
+        Self {
+            sink,
+            refused: refused.copied(),
+        }
+    }
+
+    fn check(&self, name: &str) -> Result<()> {
+        match self.refused {
+            Some(RefusedHooks(refuses)) if refuses(name) => Err(Error::validation(format!(
+                "hook {} already registered",
+                name
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+impl<S: HookSink> HookSink for Refusing<'_, S> {
+    fn add_filter(&self, name: &str, callback: FilterFn, priority: i32) -> Result<()> {
+        self.check(name)?;
+        self.sink.add_filter(name, callback, priority)
+    }
+
+    fn add_action(&self, name: &str, callback: ActionFn, priority: i32) -> Result<()> {
+        self.check(name)?;
+        self.sink.add_action(name, callback, priority)
+    }
+
+    fn remove_own_hook(&self, name: &str) {
+        self.sink.remove_own_hook(name);
+    }
+}
@@ -8,218 +8,1185 @@
 //! - Use hooks (actions and filters)
 //! - Store plugin settings
 
+mod attrs;
+mod audit;
+mod bundle;
+mod clock;
+mod context;
+mod css;
+mod hooks;
+mod logging;
+mod partial;
+mod provider;
+mod remote;
+mod render;
+mod rotation;
+mod store;
+mod template;
+mod usage;
+#[cfg(feature = "native")]
+mod watch;
+
+pub use attrs::parse_shortcode_attrs;
+pub use audit::{AuditEntry, MAX_AUDIT_ENTRIES};
+pub use bundle::SettingsBundle;
+pub use clock::{normalize_timezone, Clock, FixedClock, HostClock};
+#[cfg(feature = "native")]
+pub use clock::SystemClock;
+pub use context::{
+    CurrentUser, ExportSecret, PostMeta, RequestState, ReturningVisitor, SiteId, SiteName,
+    UserAgent,
+};
+pub use css::{
+    StylingMode, CSS_COMPONENTS, DEFAULT_DATE_CSS, DEFAULT_WIDGET_CSS, MIN_CONTRAST_RATIO,
+};
+pub use hooks::RegistrationReport;
+pub use logging::LogLevel;
+pub use partial::PartialHelloWorldSettings;
+pub use provider::{
+    GreetingProvider, ProviderContext, RemoteProvider, RotationProvider, StaticProvider,
+};
+pub use remote::{CallbackFetcher, FetchFuture, GreetingFetcher, GreetingSource};
+#[cfg(feature = "native")]
+pub use remote::HttpFetcher;
+pub use render::{
+    ordinal_suffix, ContentType, DateBackend, DateMode, Direction, GreetingHtmlMode,
+    DEFAULT_DATE_FORMAT,
+};
+pub use rotation::Rotation;
+pub use store::{MemoryStore, SettingsStore};
+
 use async_trait::async_trait;
-use chrono::Utc;
+use bitflags::bitflags;
+use chrono::{DateTime, Utc, Weekday};
 use rustpress_core::context::AppContext;
-use rustpress_core::error::Result;
+use rustpress_core::error::{Error, Result};
 use rustpress_core::hook::HookRegistry;
 use rustpress_core::plugin::{Plugin, PluginInfo, PluginState};
-use semver::Version;
+use semver::{Version, VersionReq};
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
-use parking_lot::RwLock;
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Once, Weak};
+#[cfg(feature = "native")]
+use std::time::Duration;
+use parking_lot::{Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use tokio::sync::broadcast;
+use unicode_segmentation::UnicodeSegmentation;
+use audit::AuditLog;
+use hooks::{Gated, HookSink, Namespaced, Recording};
+use logging::{plugin_log, LogConfig};
+use remote::RemoteCache;
+use render::RenderEnv;
+use usage::UsageCounter;
+
+bitflags! {
+    /// Features registered with the hook system on activation
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(transparent)]
+    pub struct FeatureSet: u8 {
+        /// The `[hello]` family of shortcodes
+        const SHORTCODES = 1;
+        /// The Hello World widget
+        const WIDGETS = 1 << 1;
+        /// Default or custom CSS in `wp_head`
+        const HEAD_CSS = 1 << 2;
+        /// The "Powered by" footer on post content
+        const CONTENT_FILTER = 1 << 3;
+    }
+}
+
+impl Default for FeatureSet {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+/// A named widget, rendered by its own widget hook and by
+/// `[hello_widget id="..."]`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WidgetConfig {
+    /// Unique id; letters, digits, `-` and `_`
+    pub id: String,
+    /// Widget title
+    pub title: String,
+    /// Body text; the greeting when unset
+    #[serde(default)]
+    pub text: Option<String>,
+}
 
 /// Plugin settings
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct HelloWorldSettings {
     pub greeting_text: String,
-    pub show_date: bool,
+    /// Fills the `{name}` placeholder of greetings, and the `name` template
+    /// variable, when the visitor is anonymous
+    pub default_name: String,
+    /// Greeting for visitors the host marks as new with [`ReturningVisitor`]
+    pub first_visit_greeting: Option<String>,
+    /// Greetings to rotate through instead of `greeting_text`
+    pub greetings: Vec<String>,
+    /// How the greeting is picked from `greetings`
+    pub rotation: Rotation,
+    /// Where the default greeting comes from
+    pub greeting_source: GreetingSource,
+    /// The host has no network: never fetch the remote greeting
+    pub offline: bool,
+    /// Seconds after which a remote greeting that hasn't been refreshed is
+    /// stale, degrading [`HelloWorldPlugin::health_check`]; `None` never
+    pub remote_stale_after_secs: Option<u64>,
+    /// What happens to markup in the greeting in HTML output
+    pub greeting_html: GreetingHtmlMode,
+    /// Whether the date line is shown; `show_date` is accepted as a
+    /// deprecated boolean alias
+    #[serde(alias = "show_date")]
+    pub date_mode: DateMode,
+    /// Leave the date out on Saturdays and Sundays
+    pub hide_date_weekends: bool,
+    /// Put the date in the greeting element instead of on its own line
+    pub date_inline: bool,
+    /// Text between the greeting and an inline date
+    pub greeting_date_separator: String,
     pub custom_css: String,
+    /// Declarations replacing those of one default stylesheet block, keyed
+    /// by component; see [`CSS_COMPONENTS`]. Ignored with `custom_css` set
+    pub css_overrides: HashMap<String, String>,
+    /// Most verbose log level this instance emits
+    pub log_level: LogLevel,
+    /// Log key=value fields instead of prose, for log aggregation
+    pub compact_logs: bool,
+    /// Prepended to the plugin id to form the settings store key, for hosts
+    /// that namespace plugin data
+    pub storage_key_prefix: String,
+    /// How many times a settings save is attempted before giving up
+    pub save_max_attempts: u32,
+    /// How many times loading settings on startup is attempted before
+    /// carrying on with the current ones
+    pub load_max_attempts: u32,
+    /// Whether styles go in `wp_head`, inline on the markup, or nowhere
+    pub styling_mode: StylingMode,
+    /// Emit no `style` attributes, for AMP pages: every style goes in one
+    /// `<style amp-custom>` block in `wp_head` instead, even with
+    /// `inline_style` styling
+    pub amp_mode: bool,
+    /// Put each element of the widget and footer markup on its own line,
+    /// indented by nesting, instead of emitting it on one line
+    pub pretty_html: bool,
+    /// Start color of the default greeting background gradient
+    pub gradient_start: String,
+    /// End color of the default greeting background gradient
+    pub gradient_end: String,
+    /// Use dark greeting text when white would have less than
+    /// [`MIN_CONTRAST_RATIO`] contrast against the gradient. Note that the
+    /// default gradient is one of those
+    pub enforce_contrast: bool,
+    /// Append the "Powered by" footer to post content
+    pub append_footer: bool,
+    /// Where the "Powered by" footer links to, if anywhere
+    pub footer_url: Option<String>,
+    /// Priority of the `the_content` filter appending the footer; higher
+    /// runs later
+    pub content_filter_priority: i32,
+    /// Characters greetings may not contain
+    pub forbidden_chars: Vec<char>,
+    /// Shrink the greeting on narrow screens
+    pub responsive: bool,
+    /// Pick a gradient from a built-in palette on every page instead of
+    /// `gradient_start`/`gradient_end`
+    pub random_gradient: bool,
+    /// Skip the head stylesheet when another instance already emitted the
+    /// same one for the request
+    pub dedupe_head_css: bool,
+    /// Most bytes of CSS the head `<style>` block may hold, for CSP setups
+    /// that cap inline styles. Past it, `stylesheet_url` is linked instead,
+    /// or without one the styles are cut short
+    pub max_inline_css_bytes: Option<usize>,
+    /// Where the host serves [`HelloWorldPlugin::stylesheet`], linked in
+    /// place of styles over `max_inline_css_bytes`
+    pub stylesheet_url: Option<String>,
+    /// `aria-live` politeness of the greeting container, one of
+    /// [`ARIA_LIVE_VALUES`], so screen readers announce rotated greetings
+    pub aria_live: Option<String>,
+    /// Text direction of the greeting container; unset, it follows the page
+    pub direction: Option<Direction>,
+    /// Render only the bare greeting, without date, styles or template, for
+    /// requests whose [`UserAgent`] is a known crawler
+    pub skip_for_bots: bool,
+    /// IANA timezone the date and weekday are computed in
+    pub timezone: String,
+    /// `(label, IANA timezone)` pairs listed by `[hello_clocks]`
+    pub clocks: Vec<(String, String)>,
+    /// Greetings that replace `greeting_text` on particular weekdays
+    pub weekday_greetings: HashMap<Weekday, String>,
+    /// chrono `strftime` format of the date line
+    pub date_format: String,
+    /// Write the day of the month as an ordinal ("5th")
+    pub date_ordinals: bool,
+    /// What formats the date line
+    pub date_backend: DateBackend,
+    /// BCP 47 locale of dates formatted by [`DateBackend::Icu`], e.g. `fr`
+    pub date_locale: String,
+    /// Which features `activate` registers
+    pub features: FeatureSet,
+    /// Hooks do nothing before this time, e.g. for seasonal greetings
+    pub active_from: Option<DateTime<Utc>>,
+    /// Hooks do nothing from this time on
+    pub active_until: Option<DateTime<Utc>>,
+    /// Text of the widgets instead of `greeting_text`, if set
+    pub widget_greeting_text: Option<String>,
+    /// Extra classes for the widget wrapper, e.g. for grid systems
+    pub widget_extra_classes: Vec<String>,
+    /// Truncate widget text beyond this many characters (graphemes)
+    pub widget_max_chars: Option<usize>,
+    /// Named widgets in addition to the default one, at most
+    /// [`MAX_WIDGETS`]
+    pub widgets: Vec<WidgetConfig>,
+    /// Mark `[hello_widget]` ids that aren't configured instead of
+    /// rendering nothing
+    pub strict_widgets: bool,
+    /// Wrap shortcode and widget output in `<!-- hello-world:start -->` /
+    /// `<!-- hello-world:end -->` comments
+    pub debug_markers: bool,
+    /// Turn a panic while rendering a shortcode or widget into the plain
+    /// greeting instead of letting it unwind into the host
+    pub catch_panics: bool,
+    /// Handlebars template replacing the built-in greeting markup. Has
+    /// access to `greeting`, `date`, `site` and `user`, and to `post_<key>`
+    /// for each entry of the post's [`PostMeta`]. Its output keeps only a
+    /// fixed set of tags and attributes, without scripts, styles or event
+    /// handlers.
+    pub template: Option<String>,
+    /// Milliseconds a template may take to render before the built-in
+    /// greeting is used instead; 0 disables the limit
+    pub template_timeout_ms: u64,
+    /// When the settings last changed; maintained by the plugin
+    pub last_updated: Option<DateTime<Utc>>,
 }
 
 impl Default for HelloWorldSettings {
     fn default() -> Self {
         Self {
             greeting_text: "Hello, World!".to_string(),
-            show_date: true,
+            default_name: "World".to_string(),
+            first_visit_greeting: None,
+            greetings: Vec::new(),
+            rotation: Rotation::default(),
+            greeting_source: GreetingSource::default(),
+            offline: false,
+            remote_stale_after_secs: None,
+            greeting_html: GreetingHtmlMode::default(),
+            date_mode: DateMode::default(),
+            hide_date_weekends: false,
+            date_inline: false,
+            greeting_date_separator: " — ".to_string(),
             custom_css: String::new(),
+            css_overrides: HashMap::new(),
+            log_level: LogLevel::default(),
+            compact_logs: false,
+            storage_key_prefix: String::new(),
+            save_max_attempts: 3,
+            load_max_attempts: 3,
+            styling_mode: StylingMode::default(),
+            amp_mode: false,
+            pretty_html: false,
+            gradient_start: css::DEFAULT_GRADIENT_START.to_string(),
+            gradient_end: css::DEFAULT_GRADIENT_END.to_string(),
+            enforce_contrast: false,
+            append_footer: true,
+            footer_url: None,
+            content_filter_priority: 99,
+            forbidden_chars: Vec::new(),
+            responsive: false,
+            random_gradient: false,
+            dedupe_head_css: true,
+            max_inline_css_bytes: None,
+            stylesheet_url: None,
+            aria_live: None,
+            direction: None,
+            skip_for_bots: false,
+            timezone: "UTC".to_string(),
+            clocks: Vec::new(),
+            weekday_greetings: HashMap::new(),
+            date_format: DEFAULT_DATE_FORMAT.to_string(),
+            date_ordinals: false,
+            date_backend: DateBackend::default(),
+            date_locale: "en".to_string(),
+            features: FeatureSet::default(),
+            active_from: None,
+            active_until: None,
+            widget_greeting_text: None,
+            widget_extra_classes: Vec::new(),
+            widget_max_chars: None,
+            widgets: Vec::new(),
+            strict_widgets: false,
+            debug_markers: false,
+            catch_panics: false,
+            template: None,
+            template_timeout_ms: 100,
+            last_updated: None,
         }
     }
 }
 
-/// The Hello World plugin
-pub struct HelloWorldPlugin {
-    info: PluginInfo,
-    state: RwLock<PluginState>,
-    settings: RwLock<HelloWorldSettings>,
-}
+/// Largest accepted `custom_css`, in bytes
+pub const MAX_CUSTOM_CSS_LEN: usize = 64 * 1024;
 
-impl HelloWorldPlugin {
-    /// Create a new instance of the plugin
-    pub fn new() -> Self {
-        let info = PluginInfo::new(
-            "hello-world",
-            "Hello World",
-            Version::new(1, 0, 0),
-        )
-        .with_description("A simple example plugin that adds a greeting shortcode and widget")
-        .with_author("RustPress Team");
+/// Most named widgets the settings may configure, each of which registers
+/// its own hook
+pub const MAX_WIDGETS: usize = 64;
+
+/// `the_content` priorities taken by core's own content filters (autop and
+/// texturize at 10, shortcodes at 11, content tags at 12, smilies at 20)
+///
+/// Sharing one makes the footer's position relative to them depend on
+/// registration order.
+pub const RESERVED_CONTENT_PRIORITIES: [i32; 4] = [10, 11, 12, 20];
+
+/// Settings updates a [`HelloWorldPlugin::subscribe`] receiver can fall
+/// behind by before it starts missing them
+pub const SETTINGS_CHANNEL_CAPACITY: usize = 16;
+
+/// Accepted values of [`HelloWorldSettings::aria_live`]
+pub const ARIA_LIVE_VALUES: [&str; 2] = ["polite", "assertive"];
 
+/// One problem found by [`HelloWorldSettings::validate_all`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ValidationIssue {
+    /// Path of the offending field, e.g. `widgets[1].id`
+    pub field: String,
+    /// Human-readable description of the problem
+    pub message: String,
+}
+
+impl ValidationIssue {
+    fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
         Self {
-            info,
-            state: RwLock::new(PluginState::Inactive),
-            settings: RwLock::new(HelloWorldSettings::default()),
+            field: field.into(),
+            message: message.into(),
         }
     }
+}
 
-    /// Get current settings
-    pub fn settings(&self) -> HelloWorldSettings {
-        self.settings.read().clone()
+impl HelloWorldSettings {
+    /// How this instance's events are emitted
+    pub(crate) fn log_config(&self) -> LogConfig {
+        LogConfig {
+            level: self.log_level,
+            compact: self.compact_logs,
+        }
     }
 
-    /// Update settings
-    pub fn update_settings(&self, settings: HelloWorldSettings) {
-        *self.settings.write() = settings;
+    /// Whether `now` falls inside the `active_from`..`active_until` window
+    pub fn is_active_at(&self, now: DateTime<Utc>) -> bool {
+        !self.active_from.is_some_and(|from| now < from)
+            && !self.active_until.is_some_and(|until| now >= until)
     }
 
-    /// Register shortcodes
-    fn register_shortcodes(&self, hooks: &HookRegistry) {
-        // [hello] shortcode
-        let settings = self.settings.read().clone();
-        hooks.add_filter("shortcode_hello", move |_content: String| {
-            let mut output = format!(
-                r#"<div class="hello-world-greeting">{}</div>"#,
-                settings.greeting_text
+    /// Check the settings are usable, failing with the first problem found
+    pub fn validate(&self) -> Result<()> {
+        match self.validate_all().into_iter().next() {
+            Some(issue) => Err(Error::validation(issue.message)),
+            None => Ok(()),
+        }
+    }
+
+    /// Every problem with the settings, for showing next to the form fields
+    pub fn validate_all(&self) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+
+        if self.greeting_text.trim().is_empty() {
+            issues.push(ValidationIssue::new(
+                "greeting_text",
+                "greeting_text must not be empty",
+            ));
+        }
+
+        let greetings = std::iter::once(("greeting_text".to_string(), &self.greeting_text))
+            .chain(
+                self.first_visit_greeting
+                    .iter()
+                    .map(|greeting| ("first_visit_greeting".to_string(), greeting)),
+            )
+            .chain(
+                self.widget_greeting_text
+                    .iter()
+                    .map(|greeting| ("widget_greeting_text".to_string(), greeting)),
+            )
+            .chain(
+                self.greetings
+                    .iter()
+                    .enumerate()
+                    .map(|(i, greeting)| (format!("greetings[{}]", i), greeting)),
+            )
+            .chain(
+                self.weekday_greetings
+                    .iter()
+                    .map(|(day, greeting)| (format!("weekday_greetings.{}", day), greeting)),
             );
+        for (field, greeting) in greetings {
+            if let Some(c) = greeting.chars().find(|c| self.forbidden_chars.contains(c)) {
+                issues.push(ValidationIssue::new(
+                    field.clone(),
+                    format!("{} contains the forbidden character {:?}", field, c),
+                ));
+            }
+        }
+
+        if let GreetingSource::Remote { url } = &self.greeting_source {
+            if !render::is_http_url(url) {
+                issues.push(ValidationIssue::new(
+                    "greeting_source.url",
+                    format!("greeting_source url must be an http(s) URL, got {:?}", url),
+                ));
+            }
+        }
+
+        if let Some(url) = &self.footer_url {
+            if !render::is_http_url(url) {
+                issues.push(ValidationIssue::new(
+                    "footer_url",
+                    format!("footer_url must be an http(s) URL, got {:?}", url),
+                ));
+            }
+        }
+
+        if let Some(url) = &self.stylesheet_url {
+            if !render::is_http_url(url) {
+                issues.push(ValidationIssue::new(
+                    "stylesheet_url",
+                    format!("stylesheet_url must be an http(s) URL, got {:?}", url),
+                ));
+            }
+        }
+
+        // Without a stylesheet to link, over-budget custom CSS would be cut
+        if let (Some(max), None) = (self.max_inline_css_bytes, &self.stylesheet_url) {
+            if self.custom_css.len() > max {
+                issues.push(ValidationIssue::new(
+                    "custom_css",
+                    format!(
+                        "custom_css is {} bytes, over max_inline_css_bytes ({}) with no \
+                         stylesheet_url to link instead",
+                        self.custom_css.len(),
+                        max
+                    ),
+                ));
+            }
+        }
+
+        if self.save_max_attempts == 0 {
+            issues.push(ValidationIssue::new(
+                "save_max_attempts",
+                "save_max_attempts must be at least 1",
+            ));
+        }
+
+        if self.load_max_attempts == 0 {
+            issues.push(ValidationIssue::new(
+                "load_max_attempts",
+                "load_max_attempts must be at least 1",
+            ));
+        }
 
-            if settings.show_date {
-                output.push_str(&format!(
-                    r#"<div class="hello-world-date">Today is {}</div>"#,
-                    Utc::now().format("%B %d, %Y")
+        if self.remote_stale_after_secs == Some(0) {
+            issues.push(ValidationIssue::new(
+                "remote_stale_after_secs",
+                "remote_stale_after_secs must be at least 1",
+            ));
+        }
+
+        if self.widget_max_chars == Some(0) {
+            issues.push(ValidationIssue::new(
+                "widget_max_chars",
+                "widget_max_chars must be at least 1",
+            ));
+        }
+
+        if let Some(live) = &self.aria_live {
+            if !ARIA_LIVE_VALUES.contains(&live.as_str()) {
+                issues.push(ValidationIssue::new(
+                    "aria_live",
+                    format!(
+                        "aria_live must be one of {:?}, got {:?}",
+                        ARIA_LIVE_VALUES, live
+                    ),
+                ));
+            }
+        }
+
+        if self.custom_css.len() > MAX_CUSTOM_CSS_LEN {
+            issues.push(ValidationIssue::new(
+                "custom_css",
+                format!(
+                    "custom_css must be at most {} bytes, got {}",
+                    MAX_CUSTOM_CSS_LEN,
+                    self.custom_css.len()
+                ),
+            ));
+        }
+
+        let mut overrides: Vec<_> = self.css_overrides.iter().collect();
+        overrides.sort();
+        for (component, declarations) in overrides {
+            let field = format!("css_overrides.{}", component);
+            if !CSS_COMPONENTS.contains(&component.as_str()) {
+                issues.push(ValidationIssue::new(
+                    field,
+                    format!(
+                        "unknown CSS component {:?}, expected one of {:?}",
+                        component, CSS_COMPONENTS
+                    ),
+                ));
+            } else if !css::is_safe_declarations(declarations) {
+                issues.push(ValidationIssue::new(
+                    field,
+                    format!(
+                        "CSS overrides can't contain braces or angle brackets, got {:?}",
+                        declarations
+                    ),
+                ));
+            }
+        }
+
+        for (field, value) in [
+            ("gradient_start", &self.gradient_start),
+            ("gradient_end", &self.gradient_end),
+        ] {
+            if !css::is_hex_color(value) {
+                issues.push(ValidationIssue::new(
+                    field,
+                    format!(
+                        "{} must be a hex color like #667eea, got {:?}",
+                        field, value
+                    ),
+                ));
+            }
+        }
+
+        for (i, class) in self.widget_extra_classes.iter().enumerate() {
+            if !render::is_valid_class_name(class) {
+                issues.push(ValidationIssue::new(
+                    format!("widget_extra_classes[{}]", i),
+                    format!(
+                        "widget_extra_classes may only contain letters, digits, - and _, got {:?}",
+                        class
+                    ),
+                ));
+            }
+        }
+
+        if self.widgets.len() > MAX_WIDGETS {
+            issues.push(ValidationIssue::new(
+                "widgets",
+                format!(
+                    "at most {} widgets may be configured, got {}",
+                    MAX_WIDGETS,
+                    self.widgets.len()
+                ),
+            ));
+        }
+
+        let mut widget_ids = std::collections::HashSet::new();
+        for (i, widget) in self.widgets.iter().enumerate() {
+            let field = format!("widgets[{}].id", i);
+            if !render::is_valid_class_name(&widget.id) {
+                issues.push(ValidationIssue::new(
+                    field,
+                    format!(
+                        "widget ids may only contain letters, digits, - and _, got {:?}",
+                        widget.id
+                    ),
+                ));
+            } else if !widget_ids.insert(widget.id.as_str()) {
+                issues.push(ValidationIssue::new(
+                    field,
+                    format!("duplicate widget id {:?}", widget.id),
+                ));
+            }
+        }
+
+        if let (Some(from), Some(until)) = (self.active_from, self.active_until) {
+            if from >= until {
+                issues.push(ValidationIssue::new(
+                    "active_until",
+                    format!(
+                        "active_until ({}) must be after active_from ({})",
+                        until, from
+                    ),
+                ));
+            }
+        }
+
+        if normalize_timezone(&self.timezone).is_err() {
+            issues.push(ValidationIssue::new(
+                "timezone",
+                format!("timezone {:?} is not a known IANA timezone", self.timezone),
+            ));
+        }
+
+        if !render::is_valid_date_format(&self.date_format) {
+            issues.push(ValidationIssue::new(
+                "date_format",
+                format!(
+                    "date_format {:?} is not a valid strftime format",
+                    self.date_format
+                ),
+            ));
+        }
+
+        if let Some(tpl) = &self.template {
+            if let Err(e) = template::check(tpl) {
+                issues.push(ValidationIssue::new(
+                    "template",
+                    format!("template is invalid: {}", e),
                 ));
             }
+        }
+
+        issues
+    }
+}
+
+/// Sample settings published in the config schema's `examples`
+///
+/// Built from the settings types rather than written as JSON, so they can't
+/// drift out of date.
+fn schema_examples() -> Vec<serde_json::Value> {
+    let team_site = HelloWorldSettings {
+        greeting_text: "Good morning, team!".to_string(),
+        timezone: "Europe/Berlin".to_string(),
+        weekday_greetings: HashMap::from([(Weekday::Fri, "Happy Friday, team!".to_string())]),
+        date_format: "%A, %B %-d".to_string(),
+        date_ordinals: true,
+        styling_mode: StylingMode::InlineStyle,
+        widgets: vec![WidgetConfig {
+            id: "sidebar1".to_string(),
+            title: "Welcome".to_string(),
+            text: Some("Thanks for stopping by.".to_string()),
+        }],
+        ..Default::default()
+    };
+
+    [HelloWorldSettings::default(), team_site]
+        .iter()
+        .map(|settings| serde_json::to_value(settings).expect("settings serialize to JSON"))
+        .collect()
+}
+
+/// Id the plugin registers under unless given another with
+/// [`HelloWorldPlugin::with_id`]
+pub const PLUGIN_ID: &str = "hello-world";
+
+/// Action fired after every `[hello]` render, with
+/// `{"length": <bytes rendered>, "attrs": <raw attribute string>}`
+///
+/// Like the shortcodes, it is suffixed for plugins with another id; see
+/// [`HelloWorldPlugin::with_id`].
+pub const SHORTCODE_RENDERED_ACTION: &str = "hello_world_shortcode_rendered";
+
+/// Environment variable that, set to `1`, keeps the plugin from activating
+pub const DISABLED_ENV: &str = "HELLO_WORLD_DISABLED";
+
+/// Prefix of the environment variables that override settings, followed by
+/// the setting's name in upper case, e.g. `HELLO_WORLD_GREETING_TEXT`
+pub const ENV_PREFIX: &str = "HELLO_WORLD_";
+
+/// Version of the settings schema, bumped whenever a setting is added,
+/// removed or changes type
+pub const SCHEMA_VERSION: u32 = 20;
+
+/// Parse settings as written by [`HelloWorldPlugin::save_settings`]
+///
+/// A leading UTF-8 byte order mark, as some external editors write, is
+/// ignored, and older formats are upgraded through [`migrate`].
+fn parse_stored_settings(json: &str) -> Result<HelloWorldSettings> {
+    let json = json.strip_prefix('\u{feff}').unwrap_or(json);
+    serde_json::from_str(json)
+        .map(migrate)
+        .and_then(serde_json::from_value)
+        .map_err(|e| Error::storage(format!("invalid stored settings: {}", e)))
+}
+
+/// Upgrade stored settings from older formats to the current one
+///
+/// Early versions stored just the greeting as a bare JSON string; it becomes
+/// `greeting_text` with everything else at its default.
+fn migrate(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::String(greeting) => serde_json::json!({ "greeting_text": greeting }),
+        value => value,
+    }
+}
+
+/// 64-bit FNV-1a, which unlike `DefaultHasher` is fixed across Rust releases
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+
+    bytes.iter().fold(OFFSET_BASIS, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+    })
+}
+
+/// Another plugin this one needs loaded first
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PluginDependency {
+    /// Id of the other plugin
+    pub id: String,
+    /// Versions of it that are compatible
+    pub version: VersionReq,
+    /// Optional dependencies only order loading when the other plugin is
+    /// installed; a missing one isn't an error
+    pub optional: bool,
+}
+
+/// Settings shared between the plugin and its hook closures
+///
+/// All lock access goes through [`SharedSettings::read`] and
+/// [`SharedSettings::write`]. `parking_lot` locks don't poison, so neither
+/// can fail today. If this moves to `std::sync::RwLock`, these two methods
+/// are the only place that has to handle poisoning, and recovering the inner
+/// guard is sound: settings are only ever replaced wholesale, so a panicking
+/// writer can't leave them half-updated.
+///
+/// Guards must stay short-lived: never hold one across an `.await` or while
+/// calling into the hook registry.
+#[derive(Clone, Default)]
+struct SharedSettings(Arc<RwLock<HelloWorldSettings>>);
+
+impl SharedSettings {
+    fn read(&self) -> RwLockReadGuard<'_, HelloWorldSettings> {
+        self.0.read()
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, HelloWorldSettings> {
+        self.0.write()
+    }
 
+    /// Run the `hook` handler's `render` on the settings
+    ///
+    /// With `catch_panics` on, a panic in `render` is logged and the plain
+    /// greeting returned instead, so it can't take the page down with it.
+    ///
+    /// In `amp_mode`, any `style` attributes left in the output, e.g. from
+    /// a template, are removed.
+    fn render_guarded(
+        &self,
+        hook: &str,
+        render: impl FnOnce(&HelloWorldSettings) -> String,
+    ) -> String {
+        let settings = self.read();
+        let output = if settings.catch_panics {
+            Self::catch_render_panic(&settings, hook, render)
+        } else {
+            render(&settings)
+        };
+        if settings.amp_mode {
+            render::strip_style_attrs(&output)
+        } else {
             output
-        }, 10);
+        }
     }
 
-    /// Register widgets
-    fn register_widgets(&self, hooks: &HookRegistry) {
-        // Hello World widget
-        let settings = self.settings.read().clone();
-        hooks.add_filter("widget_hello_world", move |_content: String| {
-            format!(
-                r#"<div class="widget hello-world-widget">
-                    <h3 class="widget-title">Greeting</h3>
-                    <div class="widget-content">
-                        <p>{}</p>
-                    </div>
-                </div>"#,
-                settings.greeting_text
-            )
-        }, 10);
+    /// `render`'s output, or the fallback greeting if it panics
+    fn catch_render_panic(
+        settings: &HelloWorldSettings,
+        hook: &str,
+        render: impl FnOnce(&HelloWorldSettings) -> String,
+    ) -> String {
+        // Nothing `render` can reach is left inconsistent by unwinding:
+        // the settings are behind a read guard and counters are atomic
+        match panic::catch_unwind(AssertUnwindSafe(|| render(settings))) {
+            Ok(output) => output,
+            Err(_) => {
+                plugin_log!(
+                    settings.log_config(),
+                    ERROR,
+                    event = "render_panicked",
+                    hook = hook;
+                    "{} handler panicked, showing the fallback greeting",
+                    hook
+                );
+                render::panic_fallback(settings)
+            }
+        }
     }
+}
 
-    /// Add custom CSS to head
-    fn add_head_css(&self, hooks: &HookRegistry) {
-        let settings = self.settings.read().clone();
-        hooks.add_action("wp_head", move || {
-            let css = if settings.custom_css.is_empty() {
-                r#"
-                .hello-world-greeting {
-                    padding: 20px;
-                    background: linear-gradient(135deg, #667eea 0%, #764ba2 100%);
-                    color: white;
-                    border-radius: 8px;
-                    margin: 20px 0;
-                    font-size: 1.5em;
-                    text-align: center;
-                }
-                .hello-world-date {
-                    text-align: center;
-                    color: #666;
-                    font-style: italic;
-                }
-                .hello-world-widget {
-                    background: #f5f5f5;
-                    padding: 15px;
-                    border-radius: 4px;
-                }
-                "#.to_string()
-            } else {
-                settings.custom_css.clone()
-            };
+/// What it takes to update the live settings, detached from the plugin so
+/// the config file watcher can apply changes from its own thread
+#[derive(Clone)]
+struct SettingsWriter {
+    settings: SharedSettings,
+    updates: Arc<Mutex<()>>,
+    audit: Arc<AuditLog>,
+    clock: Arc<dyn Clock>,
+    changes: broadcast::Sender<HelloWorldSettings>,
+}
 
-            println!("<style>{}</style>", css);
-        }, 10);
+impl SettingsWriter {
+    /// Swap in the already validated `new`, stamping `last_updated` and
+    /// auditing the change if anything changed
+    ///
+    /// Everything but the swap happens before the write lock is taken, so
+    /// renders are only held up for the swap itself. Callers hold `updates`
+    /// so the settings diffed against are still the ones replaced.
+    ///
+    /// Whatever `last_updated` `new` carries is replaced, so it's neither
+    /// reset by settings built from `Default` nor counted as a change.
+    ///
+    /// Subscribers are sent the new settings if anything changed.
+    fn commit(&self, new: HelloWorldSettings, actor: Option<&str>) {
+        self.swap(new, actor, false);
     }
 
-    /// Content filter example
-    fn add_content_filter(&self, hooks: &HookRegistry) {
-        hooks.add_filter("the_content", |content: String| {
-            // Add a small footer to all content
-            format!(
-                r#"{}
-                <div class="hello-world-footer" style="font-size: 0.8em; color: #999; margin-top: 20px; padding-top: 10px; border-top: 1px solid #eee;">
-                    Powered by Hello World Plugin
-                </div>"#,
-                content
-            )
-        }, 99); // Low priority to run last
+    /// [`SettingsWriter::commit`] for settings loaded from a store, which
+    /// keep the `last_updated` they were saved with
+    fn commit_loaded(&self, new: HelloWorldSettings) {
+        self.swap(new, None, true);
+    }
+
+    fn swap(&self, mut new: HelloWorldSettings, actor: Option<&str>, loaded: bool) {
+        let current = self.settings.read().clone();
+        if !loaded {
+            new.last_updated = current.last_updated;
+        }
+        let changed_fields = audit::changed_fields(&current, &new);
+        if changed_fields.is_empty() {
+            *self.settings.write() = new;
+            return;
+        }
+
+        let now = self.clock.now();
+        if !loaded {
+            new.last_updated = Some(now);
+        }
+        self.audit.record(now, actor, changed_fields);
+        *self.settings.write() = new.clone();
+        // Only fails when nobody is subscribed
+        let _ = self.changes.send(new);
+    }
+
+    /// Overwrite only the fields set in `partial`, rejecting the result if
+    /// it isn't valid
+    fn merge(&self, partial: PartialHelloWorldSettings) -> Result<()> {
+        self.merge_layers([partial])
+    }
+
+    /// Apply each of `layers` in turn over the current settings, rejecting
+    /// the result if it isn't valid
+    fn merge_layers(
+        &self,
+        layers: impl IntoIterator<Item = PartialHelloWorldSettings>,
+    ) -> Result<()> {
+        let _update = self.updates.lock();
+        let mut merged = self.settings.read().clone();
+        for partial in layers {
+            partial.apply_to(&mut merged);
+        }
+        merged.validate()?;
+        self.commit(merged, None);
+        Ok(())
     }
 }
 
-impl Default for HelloWorldPlugin {
-    fn default() -> Self {
-        Self::new()
+/// The settings overriding the stored ones, lowest precedence first: those
+/// in the config file at `config_file`, if any, then the environment's
+fn override_layers(
+    config_file: Option<&Path>,
+) -> Result<Vec<(&'static str, PartialHelloWorldSettings)>> {
+    let mut layers = Vec::new();
+    if let Some(path) = config_file {
+        layers.push(("file", read_config_file(path)?));
     }
+    let env = PartialHelloWorldSettings::from_env(|name| std::env::var(name).ok())?;
+    layers.push(("env", env));
+    Ok(layers)
 }
 
-#[async_trait]
-impl Plugin for HelloWorldPlugin {
-    fn info(&self) -> &PluginInfo {
-        &self.info
+/// The settings in the TOML file at `path`
+fn read_config_file(path: &Path) -> Result<PartialHelloWorldSettings> {
+    let toml = std::fs::read_to_string(path).map_err(|e| {
+        Error::storage(format!("failed to read config file {}: {}", path.display(), e))
+    })?;
+    toml::from_str(&toml)
+        .map_err(|e| Error::validation(format!("invalid config file {}: {}", path.display(), e)))
+}
+
+/// Result of [`HelloWorldPlugin::health_check`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HealthStatus {
+    /// Working as configured
+    Healthy,
+    /// Working, but not as configured, for the given reason
+    Degraded(String),
+}
+
+/// The Hello World plugin
+pub struct HelloWorldPlugin {
+    info: PluginInfo,
+    state: RwLock<PluginState>,
+    /// Shared with the registered hook closures so they render live settings
+    settings: SharedSettings,
+    /// Used for persistence when the host doesn't register a store
+    fallback_store: Arc<MemoryStore>,
+    /// Time source for everything date-dependent
+    clock: Arc<dyn Clock>,
+    /// Replaces the greeting provider the settings select, if set
+    provider: Option<Arc<dyn GreetingProvider>>,
+    /// Last fetched remote greeting, shared with the hook closures
+    remote: RemoteCache,
+    /// Renders so far, shared with the hook closures; drives the rotation
+    render_count: Arc<AtomicU64>,
+    /// Names of the hooks registered by the last activation
+    registered_hooks: RwLock<Vec<String>>,
+    /// Whether the last activation's handlers still run; deactivating
+    /// clears it, which is all that stops the handlers left on shared hooks
+    attached: Mutex<Arc<AtomicBool>>,
+    /// Outcome of the last activation's hook registration
+    registration: RwLock<RegistrationReport>,
+    /// Guards [`HelloWorldPlugin::warmup`]
+    warmed_up: Once,
+    /// Held for the whole of `activate`, so racing activations run one at
+    /// a time and only the first registers hooks
+    activation: tokio::sync::Mutex<()>,
+    /// Callbacks registered through [`HelloWorldPlugin::on_state_change`]
+    state_observers: RwLock<Vec<StateObserver>>,
+    /// Recent settings changes
+    audit: Arc<AuditLog>,
+    /// Held for the whole of a settings update, so updates apply one at a
+    /// time without holding the settings lock
+    updates: Arc<Mutex<()>>,
+    /// `[hello]` renders by attribute signature
+    usage: UsageCounter,
+    /// Sends the settings to subscribers after each change
+    changes: broadcast::Sender<HelloWorldSettings>,
+    /// TOML file whose settings override the stored ones, if set
+    config_file: Option<PathBuf>,
+    /// Debounce of the config file watcher, if the file is watched
+    #[cfg(feature = "native")]
+    config_watch: Option<Duration>,
+    /// Watches the config file between startup and shutdown
+    #[cfg(feature = "native")]
+    config_watcher: Mutex<Option<watch::ConfigWatcher>>,
+}
+
+type StateObserver = Box<dyn Fn(PluginState) + Send + Sync>;
+
+/// The plugin's info, registered as `id`
+fn plugin_info(id: &str) -> PluginInfo {
+    PluginInfo::new(id, "Hello World", Version::new(1, 0, 0))
+        .with_description("A simple example plugin that adds a greeting shortcode and widget")
+        .with_author("RustPress Team")
+}
+
+impl HelloWorldPlugin {
+    /// Create a new instance of the plugin
+    pub fn new() -> Self {
+        Self {
+            info: plugin_info(PLUGIN_ID),
+            state: RwLock::new(PluginState::Inactive),
+            settings: SharedSettings::default(),
+            fallback_store: Arc::new(MemoryStore::default()),
+            clock: clock::default_clock(),
+            provider: None,
+            remote: RemoteCache::default(),
+            render_count: Arc::new(AtomicU64::new(0)),
+            registered_hooks: RwLock::new(Vec::new()),
+            attached: Mutex::default(),
+            registration: RwLock::default(),
+            warmed_up: Once::new(),
+            activation: tokio::sync::Mutex::new(()),
+            state_observers: RwLock::new(Vec::new()),
+            audit: Arc::default(),
+            updates: Arc::default(),
+            usage: UsageCounter::default(),
+            changes: broadcast::channel(SETTINGS_CHANNEL_CAPACITY).0,
+            config_file: None,
+            #[cfg(feature = "native")]
+            config_watch: None,
+            #[cfg(feature = "native")]
+            config_watcher: Mutex::new(None),
+        }
     }
 
-    fn state(&self) -> PluginState {
-        *self.state.read()
+    /// Register as `id` instead of [`PLUGIN_ID`], so several copies can be
+    /// loaded side by side, e.g. one per tenant
+    ///
+    /// The id keys the stored settings, and the plugin's own hooks get it
+    /// as a suffix, with `-` replaced by `_`: a copy with id `tenant-a`
+    /// registers `[hello_tenant_a]` and `widget_hello_world_tenant_a`. Core
+    /// hooks such as `the_content` keep their names.
+    pub fn with_id(mut self, id: &str) -> Self {
+        self.info = plugin_info(id);
+        self
     }
 
-    async fn activate(&self, ctx: &AppContext) -> Result<()> {
-        tracing::info!("Activating Hello World plugin");
+    /// The suffix of the plugin's own hooks, unless it has the default id
+    fn hook_suffix(&self) -> Option<String> {
+        (self.info.id != PLUGIN_ID).then(|| self.info.id.replace('-', "_"))
+    }
 
-        // Load settings from database (if available)
-        // For now, use defaults
+    /// Read the time from `clock` instead of the default one
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
 
-        // Register with hook system
-        if let Some(hooks) = ctx.get::<Arc<RwLock<HookRegistry>>>() {
-            let registry = hooks.read();
-            self.register_shortcodes(&registry);
-            self.register_widgets(&registry);
-            self.add_head_css(&registry);
-            self.add_content_filter(&registry);
-        }
+    /// Take greetings from `provider` instead of the one the settings select
+    pub fn with_greeting_provider(mut self, provider: Arc<dyn GreetingProvider>) -> Self {
+        self.provider = Some(provider);
+        self
+    }
 
-        *self.state.write() = PluginState::Active;
-        tracing::info!("Hello World plugin activated successfully");
+    /// Apply the settings in the TOML file at `path` on activation
+    ///
+    /// The file only needs the settings it changes. They take precedence
+    /// over the stored settings, which fill in the rest, and are themselves
+    /// overridden by [`ENV_PREFIX`] environment variables.
+    pub fn with_config_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config_file = Some(path.into());
+        self
+    }
 
-        Ok(())
+    /// Reload the config file whenever it changes between startup and
+    /// shutdown, once it has been quiet for `debounce`
+    ///
+    /// A changed file that doesn't parse or validate is logged and the
+    /// current settings are kept.
+    #[cfg(feature = "native")]
+    pub fn with_config_watch(mut self, debounce: Duration) -> Self {
+        self.config_watch = Some(debounce);
+        self
     }
 
-    async fn deactivate(&self, _ctx: &AppContext) -> Result<()> {
-        tracing::info!("Deactivating Hello World plugin");
+    /// Whether the plugin is currently active
+    pub fn is_active(&self) -> bool {
+        matches!(*self.state.read(), PluginState::Active)
+    }
 
-        // Clean up hooks would happen here
-        // In a real implementation, we'd remove our registered hooks
+    /// Call `observer` with the new state whenever activation or
+    /// deactivation changes it
+    pub fn on_state_change(&self, observer: impl Fn(PluginState) + Send + Sync + 'static) {
+        self.state_observers.write().push(Box::new(observer));
+    }
 
-        *self.state.write() = PluginState::Inactive;
-        tracing::info!("Hello World plugin deactivated");
+    /// Move to `state` and notify the observers
+    fn set_state(&self, state: PluginState) {
+        *self.state.write() = state;
+        for observer in self.state_observers.read().iter() {
+            observer(state);
+        }
+    }
 
-        Ok(())
+    /// Get current settings
+    pub fn settings(&self) -> HelloWorldSettings {
+        self.read_settings().clone()
+    }
+
+    /// Run `f` on the current settings without cloning them
+    ///
+    /// `f` runs under the settings read lock, so it must not call back into
+    /// methods that update the settings.
+    pub fn with_settings<R>(&self, f: impl FnOnce(&HelloWorldSettings) -> R) -> R {
+        f(&self.read_settings())
+    }
+
+    /// A hash of the current settings that only changes when they do, for
+    /// invalidating caches
+    ///
+    /// Stable across runs and processes: the settings are hashed in their
+    /// JSON form, whose objects (unlike `HashMap`) have sorted keys.
+    pub fn settings_fingerprint(&self) -> u64 {
+        let json = serde_json::to_value(&*self.read_settings())
+            .expect("settings serialize to JSON")
+            .to_string();
+        fnv1a(json.as_bytes())
+    }
+
+    /// Update settings, rejecting invalid ones
+    pub fn update_settings(&self, settings: HelloWorldSettings) -> Result<()> {
+        self.update_settings_as(settings, None)
     }
 
-    async fn on_startup(&self, _ctx: &AppContext) -> Result<()> {
-        tracing::debug!("Hello World plugin startup");
+    /// Update settings on behalf of `actor`, who is named in the audit log
+    pub fn update_settings_as(
+        &self,
+        settings: HelloWorldSettings,
+        actor: Option<&str>,
+    ) -> Result<()> {
+        settings.validate()?;
+        let _update = self.updates.lock();
+        self.writer().commit(settings, actor);
         Ok(())
     }
 
-    async fn on_shutdown(&self, _ctx: &AppContext) -> Result<()> {
-        tracing::debug!("Hello World plugin shutdown");
+    /// Swap in settings read back from a store, keeping their
+    /// `last_updated`
+    fn apply_stored_settings(&self, settings: HelloWorldSettings) -> Result<()> {
+        settings.validate()?;
+        let _update = self.updates.lock();
+        self.writer().commit_loaded(settings);
         Ok(())
     }
 
-    fn config_schema(&self) -> Option<serde_json::Value> {
-        Some(serde_json::json!({
+    /// A handle for updating the live settings
+    fn writer(&self) -> SettingsWriter {
+        SettingsWriter {
+            settings: self.settings.clone(),
+            updates: Arc::clone(&self.updates),
+            audit: Arc::clone(&self.audit),
+            clock: Arc::clone(&self.clock),
+            changes: self.changes.clone(),
+        }
+    }
+
+    /// A receiver of the settings after each update that changes them
+    ///
+    /// Only settings committed after subscribing are received. A receiver
+    /// more than [`SETTINGS_CHANNEL_CAPACITY`] updates behind misses the
+    /// oldest ones: its next `recv` reports how many were skipped with
+    /// `RecvError::Lagged`, and the one after picks up from the oldest
+    /// update still held, so a subscriber that only needs the latest
+    /// settings can treat `Lagged` as a prompt to read on.
+    pub fn subscribe(&self) -> broadcast::Receiver<HelloWorldSettings> {
+        self.changes.subscribe()
+    }
+
+    /// Recent settings changes, oldest first
+    pub fn audit_log(&self) -> Vec<AuditEntry> {
+        self.audit.entries()
+    }
+
+    /// Overwrite only the fields set in `partial`, rejecting the result if
+    /// it isn't valid
+    pub fn merge_settings(&self, partial: PartialHelloWorldSettings) -> Result<()> {
+        self.writer().merge(partial)
+    }
+
+    /// Read access to the live settings; see [`SharedSettings`] for the
+    /// locking rules
+    fn read_settings(&self) -> RwLockReadGuard<'_, HelloWorldSettings> {
+        self.settings.read()
+    }
+
+    /// Plugins that must be loaded before this one
+    ///
+    /// Hello World has none. Integrations, e.g. with a theme plugin, are
+    /// declared here as optional dependencies.
+    pub fn dependencies(&self) -> Vec<PluginDependency> {
+        Vec::new()
+    }
+
+    /// The settings schema with its [`SCHEMA_VERSION`], so clients can
+    /// cache it until the version changes
+    pub fn config_schema_versioned(&self) -> (u32, serde_json::Value) {
+        let schema = serde_json::json!({
             "type": "object",
             "properties": {
                 "greeting_text": {
@@ -228,58 +1195,2923 @@ impl Plugin for HelloWorldPlugin {
                     "description": "The text to display in the greeting",
                     "default": "Hello, World!"
                 },
-                "show_date": {
+                "default_name": {
+                    "type": "string",
+                    "title": "Default Name",
+                    "description": "Name used for {name} in greetings when the visitor is anonymous",
+                    "default": "World"
+                },
+                "first_visit_greeting": {
+                    "type": ["string", "null"],
+                    "title": "First Visit Greeting",
+                    "description": "Greeting for visitors the host marks as new; everyone else sees the usual greeting",
+                    "default": null
+                },
+                "greetings": {
+                    "type": "array",
+                    "title": "Greetings",
+                    "description": "Greetings to rotate through instead of the greeting text",
+                    "items": { "type": "string" },
+                    "default": []
+                },
+                "rotation": {
+                    "type": "string",
+                    "title": "Rotation",
+                    "description": "How the greeting is picked from the list: fixed uses the greeting text",
+                    "enum": ["fixed", "sequential", "random", "daily"],
+                    "default": "fixed"
+                },
+                "greeting_source": {
+                    "type": "object",
+                    "title": "Greeting Source",
+                    "description": "Use greeting_text, or fetch the greeting from a URL",
+                    "oneOf": [
+                        {
+                            "properties": { "type": { "const": "local" } },
+                            "required": ["type"]
+                        },
+                        {
+                            "properties": {
+                                "type": { "const": "remote" },
+                                "url": { "type": "string", "format": "uri" }
+                            },
+                            "required": ["type", "url"]
+                        }
+                    ],
+                    "default": { "type": "local" }
+                },
+                "offline": {
                     "type": "boolean",
-                    "title": "Show Date",
-                    "description": "Whether to show the current date",
-                    "default": true
+                    "title": "Offline",
+                    "description": "Never fetch the remote greeting; use the cached one or greeting_text",
+                    "default": false
+                },
+                "remote_stale_after_secs": {
+                    "type": ["integer", "null"],
+                    "title": "Remote Greeting Max Age",
+                    "description": "Seconds after which a remote greeting that hasn't been refreshed is reported as stale",
+                    "minimum": 1,
+                    "default": null
+                },
+                "greeting_html": {
+                    "type": "string",
+                    "title": "Greeting HTML",
+                    "description": "escape shows tags as text, safe_subset keeps b, i, em, strong and br, raw outputs the greeting unchanged (trusted admins only)",
+                    "enum": ["escape", "safe_subset", "raw"],
+                    "default": "safe_subset"
+                },
+                "date_mode": {
+                    "type": "string",
+                    "title": "Date",
+                    "description": "Show the current date, or hide it",
+                    "enum": ["absolute", "hidden"],
+                    "default": "absolute"
+                },
+                "hide_date_weekends": {
+                    "type": "boolean",
+                    "title": "Hide Date on Weekends",
+                    "description": "Leave the date out on Saturdays and Sundays",
+                    "default": false
+                },
+                "date_inline": {
+                    "type": "boolean",
+                    "title": "Inline Date",
+                    "description": "Show the date in the greeting itself instead of on its own line",
+                    "default": false
+                },
+                "greeting_date_separator": {
+                    "type": "string",
+                    "title": "Greeting/Date Separator",
+                    "description": "Text between the greeting and an inline date",
+                    "default": " — "
                 },
                 "custom_css": {
                     "type": "string",
                     "title": "Custom CSS",
                     "description": "Custom CSS styles for the plugin",
                     "default": ""
-                }
-            }
-        }))
-    }
-}
-
-/// Plugin entry point - called by the plugin loader
-pub fn create_plugin() -> Arc<dyn Plugin> {
-    Arc::new(HelloWorldPlugin::new())
-}
-
+                },
+                "css_overrides": {
+                    "type": "object",
+                    "title": "CSS Overrides",
+                    "description": "Declarations replacing a default style block, keyed by greeting, date, widget or footer",
+                    "propertyNames": { "enum": CSS_COMPONENTS },
+                    "additionalProperties": { "type": "string" },
+                    "default": {}
+                },
+                "log_level": {
+                    "type": "string",
+                    "title": "Log Level",
+                    "description": "Most verbose log level the plugin emits",
+                    "enum": ["error", "warn", "info", "debug", "trace"],
+                    "default": "debug"
+                },
+                "compact_logs": {
+                    "type": "boolean",
+                    "title": "Compact Logs",
+                    "description": "Log single-line key=value fields instead of messages, for log aggregation",
+                    "default": false
+                },
+                "storage_key_prefix": {
+                    "type": "string",
+                    "title": "Storage Key Prefix",
+                    "description": "Prepended to the plugin id to form the key settings are stored under",
+                    "default": ""
+                },
+                "save_max_attempts": {
+                    "type": "integer",
+                    "title": "Save Attempts",
+                    "description": "How many times saving settings is attempted before failing",
+                    "minimum": 1,
+                    "default": 3
+                },
+                "load_max_attempts": {
+                    "type": "integer",
+                    "title": "Load Attempts",
+                    "description": "How many times loading settings on startup is attempted before the current settings are kept",
+                    "minimum": 1,
+                    "default": 3
+                },
+                "styling_mode": {
+                    "type": "string",
+                    "title": "Styling Mode",
+                    "description": "Emit styles in a <style> block in the head, inline on the elements, or not at all",
+                    "enum": ["head_css", "inline_style", "none"],
+                    "default": "head_css"
+                },
+                "amp_mode": {
+                    "type": "boolean",
+                    "title": "AMP Mode",
+                    "description": "Leave out style attributes and emit all styles in a single <style amp-custom> block",
+                    "default": false
+                },
+                "pretty_html": {
+                    "type": "boolean",
+                    "title": "Pretty HTML",
+                    "description": "Emit widget and footer markup one indented element per line instead of on a single line",
+                    "default": false
+                },
+                "gradient_start": {
+                    "type": "string",
+                    "title": "Gradient Start",
+                    "description": "Start color of the default greeting background",
+                    "pattern": "^#([0-9a-fA-F]{3}|[0-9a-fA-F]{6})$",
+                    "default": "#667eea"
+                },
+                "gradient_end": {
+                    "type": "string",
+                    "title": "Gradient End",
+                    "description": "End color of the default greeting background",
+                    "pattern": "^#([0-9a-fA-F]{3}|[0-9a-fA-F]{6})$",
+                    "default": "#764ba2"
+                },
+                "enforce_contrast": {
+                    "type": "boolean",
+                    "title": "Enforce Contrast",
+                    "description": "Use dark greeting text when white would have less than 4.5:1 contrast against the gradient",
+                    "default": false
+                },
+                "append_footer": {
+                    "type": "boolean",
+                    "title": "Footer",
+                    "description": "Append the \"Powered by\" footer to post content",
+                    "default": true
+                },
+                "content_filter_priority": {
+                    "type": "integer",
+                    "title": "Content Filter Priority",
+                    "description": "Priority of the filter appending the footer; higher runs later",
+                    "default": 99
+                },
+                "footer_url": {
+                    "type": ["string", "null"],
+                    "title": "Footer Link",
+                    "description": "http(s) URL the \"Powered by\" footer links to",
+                    "format": "uri",
+                    "default": null
+                },
+                "forbidden_chars": {
+                    "type": "array",
+                    "title": "Forbidden Characters",
+                    "description": "Characters greetings may not contain",
+                    "items": { "type": "string", "minLength": 1, "maxLength": 1 },
+                    "default": []
+                },
+                "responsive": {
+                    "type": "boolean",
+                    "title": "Responsive",
+                    "description": "Use a smaller greeting on screens up to 600px wide",
+                    "default": false
+                },
+                "random_gradient": {
+                    "type": "boolean",
+                    "title": "Random Gradient",
+                    "description": "Pick a gradient from a built-in palette on every page instead of the configured colors",
+                    "default": false
+                },
+                "dedupe_head_css": {
+                    "type": "boolean",
+                    "title": "Deduplicate Head CSS",
+                    "description": "Skip the head stylesheet when another Hello World instance already emitted the same one on the page",
+                    "default": true
+                },
+                "max_inline_css_bytes": {
+                    "type": ["integer", "null"],
+                    "title": "Inline CSS Budget",
+                    "description": "Most bytes of CSS to inline in the head; larger styles are linked from the stylesheet URL or cut short",
+                    "minimum": 0,
+                    "default": null
+                },
+                "stylesheet_url": {
+                    "type": ["string", "null"],
+                    "title": "Stylesheet URL",
+                    "description": "http(s) URL where the host serves the plugin's stylesheet, linked when it is over the inline CSS budget",
+                    "format": "uri",
+                    "default": null
+                },
+                "aria_live": {
+                    "type": ["string", "null"],
+                    "title": "ARIA Live Region",
+                    "description": "aria-live value of the greeting, so screen readers announce greetings changed on the page",
+                    "enum": ["polite", "assertive", null],
+                    "default": null
+                },
+                "direction": {
+                    "type": ["string", "null"],
+                    "title": "Text Direction",
+                    "description": "dir of the greeting: auto for greetings mixing scripts, ltr, or rtl for Arabic and Hebrew",
+                    "enum": ["auto", "ltr", "rtl", null],
+                    "default": null
+                },
+                "skip_for_bots": {
+                    "type": "boolean",
+                    "title": "Lean Output for Crawlers",
+                    "description": "Render only the bare greeting, without date, styles or template, for known search engine and social media crawlers",
+                    "default": false
+                },
+                "timezone": {
+                    "type": "string",
+                    "title": "Timezone",
+                    "description": "IANA timezone used for the date and weekday, e.g. Europe/Paris",
+                    "default": "UTC"
+                },
+                "clocks": {
+                    "type": "array",
+                    "title": "Clocks",
+                    "description": "[label, IANA timezone] pairs listed by [hello_clocks]",
+                    "items": {
+                        "type": "array",
+                        "prefixItems": [{ "type": "string" }, { "type": "string" }],
+                        "minItems": 2,
+                        "maxItems": 2
+                    },
+                    "default": []
+                },
+                "weekday_greetings": {
+                    "type": "object",
+                    "title": "Weekday Greetings",
+                    "description": "Greetings used instead of the default on specific weekdays, keyed Mon to Sun",
+                    "propertyNames": { "enum": ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"] },
+                    "additionalProperties": { "type": "string" },
+                    "default": {}
+                },
+                "date_format": {
+                    "type": "string",
+                    "title": "Date Format",
+                    "description": "strftime format of the date, e.g. %B %d, %Y",
+                    "default": "%B %d, %Y"
+                },
+                "date_ordinals": {
+                    "type": "boolean",
+                    "title": "Ordinal Dates",
+                    "description": "Write the day of the month as an ordinal, e.g. 5th",
+                    "default": false
+                },
+                "date_backend": {
+                    "type": "string",
+                    "title": "Date Backend",
+                    "description": "chrono (date_format, English names) or icu (localized long dates; needs the icu feature)",
+                    "enum": ["chrono", "icu"],
+                    "default": "chrono"
+                },
+                "date_locale": {
+                    "type": "string",
+                    "title": "Date Locale",
+                    "description": "BCP 47 locale of dates formatted by the icu backend, e.g. fr",
+                    "default": "en"
+                },
+                "features": {
+                    "type": "string",
+                    "title": "Features",
+                    "description": "Features to enable, separated by |: SHORTCODES, WIDGETS, HEAD_CSS, CONTENT_FILTER",
+                    "default": "SHORTCODES | WIDGETS | HEAD_CSS | CONTENT_FILTER"
+                },
+                "active_from": {
+                    "type": ["string", "null"],
+                    "title": "Active From",
+                    "description": "RFC 3339 time before which the plugin's hooks do nothing",
+                    "format": "date-time",
+                    "default": null
+                },
+                "active_until": {
+                    "type": ["string", "null"],
+                    "title": "Active Until",
+                    "description": "RFC 3339 time from which the plugin's hooks do nothing",
+                    "format": "date-time",
+                    "default": null
+                },
+                "widget_greeting_text": {
+                    "type": ["string", "null"],
+                    "title": "Widget Greeting",
+                    "description": "Text of the widgets when it should differ from the greeting text",
+                    "default": null
+                },
+                "widget_extra_classes": {
+                    "type": "array",
+                    "title": "Extra Widget Classes",
+                    "description": "CSS classes added to the widget wrapper",
+                    "items": { "type": "string", "pattern": "^[A-Za-z0-9_-]+$" },
+                    "default": []
+                },
+                "widget_max_chars": {
+                    "type": ["integer", "null"],
+                    "title": "Widget Text Limit",
+                    "description": "Characters of widget text to show before truncating with an ellipsis",
+                    "minimum": 1,
+                    "default": null
+                },
+                "widgets": {
+                    "type": "array",
+                    "title": "Widgets",
+                    "description": "Named widgets, each registered as widget_hello_world_<id> and usable via [hello_widget id=\"...\"]",
+                    "maxItems": MAX_WIDGETS,
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "id": { "type": "string", "pattern": "^[A-Za-z0-9_-]+$" },
+                            "title": { "type": "string" },
+                            "text": { "type": ["string", "null"] }
+                        },
+                        "required": ["id", "title"]
+                    },
+                    "default": []
+                },
+                "strict_widgets": {
+                    "type": "boolean",
+                    "title": "Strict Widgets",
+                    "description": "Render a comment (or with debug markers, a placeholder) for unknown [hello_widget] ids",
+                    "default": false
+                },
+                "debug_markers": {
+                    "type": "boolean",
+                    "title": "Debug Markers",
+                    "description": "Wrap the plugin's output in HTML comments marking where it starts and ends",
+                    "default": false
+                },
+                "catch_panics": {
+                    "type": "boolean",
+                    "title": "Catch Render Panics",
+                    "description": "Show the plain greeting instead of failing the page when rendering a shortcode or widget panics",
+                    "default": false
+                },
+                "template": {
+                    "type": ["string", "null"],
+                    "title": "Template",
+                    "description": "Handlebars template for the greeting; may use {{greeting}}, {{date}}, {{site}}, {{user}}, {{name}} and {{post_<key>}} for post metadata",
+                    "default": null
+                },
+                "template_timeout_ms": {
+                    "type": "integer",
+                    "title": "Template Timeout",
+                    "description": "Milliseconds a template may take to render before the built-in greeting is used; 0 for no limit",
+                    "minimum": 0,
+                    "default": 100
+                },
+                "last_updated": {
+                    "type": ["string", "null"],
+                    "title": "Last Updated",
+                    "description": "When the settings last changed; set by the plugin",
+                    "format": "date-time",
+                    "readOnly": true,
+                    "default": null
+                }
+            },
+            "examples": schema_examples()
+        });
+        (SCHEMA_VERSION, schema)
+    }
+
+    /// OpenAPI 3.1 `paths` and `components` for reading and replacing the
+    /// settings over REST, for hosts that expose a settings endpoint
+    ///
+    /// Both operations use the settings schema as their body; a rejected
+    /// `PUT` responds with the [`ValidationIssue`]s found.
+    pub fn openapi_fragment(&self) -> serde_json::Value {
+        let path = format!("/plugins/{}/settings", self.info.id);
+        let json_body = |schema: &str| {
+            serde_json::json!({
+                "application/json": {
+                    "schema": { "$ref": format!("#/components/schemas/{}", schema) }
+                }
+            })
+        };
+
+        serde_json::json!({
+            "paths": {
+                path: {
+                    "get": {
+                        "operationId": "getHelloWorldSettings",
+                        "summary": "Get the Hello World settings",
+                        "responses": {
+                            "200": {
+                                "description": "The current settings",
+                                "content": json_body("HelloWorldSettings")
+                            }
+                        }
+                    },
+                    "put": {
+                        "operationId": "putHelloWorldSettings",
+                        "summary": "Replace the Hello World settings",
+                        "requestBody": {
+                            "required": true,
+                            "content": json_body("HelloWorldSettings")
+                        },
+                        "responses": {
+                            "200": {
+                                "description": "The settings as applied",
+                                "content": json_body("HelloWorldSettings")
+                            },
+                            "400": {
+                                "description": "The settings are invalid",
+                                "content": json_body("HelloWorldValidationIssues")
+                            }
+                        }
+                    }
+                }
+            },
+            "components": {
+                "schemas": {
+                    "HelloWorldSettings": self.config_schema_versioned().1,
+                    "HelloWorldValidationIssues": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "field": { "type": "string" },
+                                "message": { "type": "string" }
+                            },
+                            "required": ["field", "message"]
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    /// Render the greeting in the format the caller accepts
+    ///
+    /// `attrs` are read as the `[hello]` shortcode's, except that `accept`
+    /// takes the place of their `format`.
+    pub fn render(&self, attrs: &str, accept: ContentType) -> String {
+        render::shortcode_as(
+            &self.read_settings(),
+            attrs,
+            accept,
+            &self.render_env(self.clock.now()),
+        )
+    }
+
+    /// Render `[hello]` as it would appear at `date`, e.g. to preview a
+    /// scheduled post
+    pub fn render_for_date(&self, date: DateTime<Utc>, attrs: &str) -> String {
+        render::shortcode(&self.read_settings(), attrs, &self.render_env(date))
+    }
+
+    /// Render the greeting and date as plain text, for feeds and APIs that
+    /// don't accept markup. Same output as `[hello format="text"]`.
+    pub fn render_plain(&self, attrs: &str) -> String {
+        self.render(attrs, ContentType::Text)
+    }
+
+    /// Render the greeting for HTML email, with all styles inlined
+    pub fn render_email(&self) -> String {
+        render::greeting_email(&self.read_settings(), &self.render_env(self.clock.now()))
+    }
+
+    /// One fragment of the greeting, for page builders that lay the pieces
+    /// out themselves
+    ///
+    /// The slots are `greeting` (the greeting without the date), `date` (the
+    /// "Today is" line, empty when the date is hidden), `widget` and `footer`
+    /// (empty when `append_footer` is off). Unknown slots give `None`.
+    pub fn render_slot(&self, slot: &str) -> Option<String> {
+        render::slot(&self.read_settings(), slot, &self.render_env(self.clock.now()))
+    }
+
+    /// The greeting a request would get, as a single-line value for an
+    /// `X-Hello-World-Greeting` debugging header
+    ///
+    /// Doesn't count as a render, so it leaves the rotation where it is.
+    pub fn greeting_header_value(&self) -> String {
+        let env = RenderEnv {
+            remote_greeting: self.remote.greeting(),
+            remote_fetched_at: self.remote.fetched_at(),
+            rotation_step: self.render_count.load(Ordering::Relaxed),
+            provider: self.provider.clone(),
+            ..RenderEnv::at(self.clock.now())
+        };
+        render::header_value(&self.read_settings(), &env)
+    }
+
+    /// Render the greeting as an SVG badge for READMEs and status pages,
+    /// drawn on the configured gradient
+    pub fn render_svg_badge(&self) -> String {
+        render::svg_badge(&self.read_settings(), &self.render_env(self.clock.now()))
+    }
+
+    /// Renders so far, counting every greeting shown by a shortcode, widget
+    /// or render method
+    pub fn render_count(&self) -> u64 {
+        self.render_count.load(Ordering::Relaxed)
+    }
+
+    /// What `[hello_stats]` shows: the render count and when the settings
+    /// last changed
+    pub fn render_stats(&self) -> String {
+        render::stats_html(&self.read_settings(), self.render_count(), self.clock.now())
+    }
+
+    /// The styles `wp_head` emits, without the `<style>` element, for the
+    /// host to serve at `stylesheet_url`
+    pub fn stylesheet(&self) -> String {
+        let settings = self.read_settings();
+        let seed = RandomState::new().hash_one(());
+        if settings.amp_mode {
+            css::amp_css(&settings, seed)
+        } else {
+            css::head_css_seeded(&settings, seed).into_owned()
+        }
+    }
+
+    /// The styles `wp_head` emits in `amp_mode`, without the `<style>`
+    /// element, for hosts that build their page's `amp-custom` block
+    /// themselves
+    pub fn amp_css(&self) -> String {
+        css::amp_css(&self.read_settings(), RandomState::new().hash_one(()))
+    }
+
+    /// The environment for a render at `now` outside of a request
+    fn render_env(&self, now: DateTime<Utc>) -> RenderEnv {
+        RenderEnv {
+            remote_greeting: self.remote.greeting(),
+            remote_fetched_at: self.remote.fetched_at(),
+            rotation_step: self.render_count.fetch_add(1, Ordering::Relaxed),
+            provider: self.provider.clone(),
+            ..RenderEnv::detached(now)
+        }
+    }
+
+    /// Render `count` greetings in a row, advancing the rotation for each,
+    /// e.g. for the slides of a carousel
+    ///
+    /// With `dedupe_adjacent`, a greeting identical to the one before it is
+    /// dropped, so the series may come out shorter than `count`.
+    pub fn render_greeting_series(&self, count: usize, dedupe_adjacent: bool) -> Vec<String> {
+        let settings = self.read_settings();
+        let mut series: Vec<String> = (0..count)
+            .map(|_| {
+                let env = self.render_env(self.clock.now());
+                render::render(&settings, ContentType::Html, &env)
+            })
+            .collect();
+        if dedupe_adjacent {
+            series.dedup();
+        }
+        series
+    }
+
+    /// Fetch the remote greeting into the cache
+    ///
+    /// Does nothing unless the source is [`GreetingSource::Remote`], and
+    /// never touches the network when `offline` is set; renders then keep
+    /// using the last fetched greeting, or `greeting_text` if there is none.
+    /// A failed fetch is not retried.
+    pub async fn refresh_remote_greeting(&self, ctx: &AppContext) -> Result<()> {
+        let (source, offline) = {
+            let settings = self.read_settings();
+            (settings.greeting_source.clone(), settings.offline)
+        };
+        let GreetingSource::Remote { url } = source else {
+            return Ok(());
+        };
+        if offline {
+            plugin_log!(
+                self.log_config(),
+                DEBUG,
+                event = "remote_fetch_skipped",
+                reason = "offline";
+                "offline, not fetching remote greeting"
+            );
+            return Ok(());
+        }
+        let Some(fetcher) = remote::fetcher_from_context(ctx) else {
+            plugin_log!(
+                self.log_config(),
+                DEBUG,
+                event = "remote_fetch_skipped",
+                reason = "no_fetcher";
+                "no GreetingFetcher available, not fetching remote greeting"
+            );
+            return Ok(());
+        };
+
+        match fetcher.fetch(&url).await {
+            Ok(greeting) => {
+                self.remote.set(greeting, self.clock.now());
+                Ok(())
+            }
+            Err(e) => {
+                self.remote.set_error(e.to_string());
+                Err(e)
+            }
+        }
+    }
+
+    /// Whether the plugin is working as configured
+    ///
+    /// Degraded while the remote greeting is older than
+    /// `remote_stale_after_secs`, i.e. refreshes have stopped succeeding.
+    pub fn health_check(&self) -> HealthStatus {
+        let settings = self.read_settings();
+        if !matches!(settings.greeting_source, GreetingSource::Remote { .. }) {
+            return HealthStatus::Healthy;
+        }
+        let now = self.clock.now();
+        match self.remote.fetched_at() {
+            Some(fetched_at)
+                if remote::is_stale(fetched_at, settings.remote_stale_after_secs, now) =>
+            {
+                let mut reason = format!(
+                    "remote greeting is stale, last fetched {}",
+                    render::time_ago(fetched_at, now)
+                );
+                if let Some(error) = self.remote.last_error() {
+                    reason.push_str(&format!("; last refresh failed: {}", error));
+                }
+                HealthStatus::Degraded(reason)
+            }
+            _ => HealthStatus::Healthy,
+        }
+    }
+
+    /// Render the greeting, widget and stylesheet once, so the first real
+    /// request doesn't pay for parsing the template and building the CSS
+    ///
+    /// Only the first call does anything. Renders directly rather than
+    /// through the hook registry, so no hooks are registered.
+    pub fn warmup(&self) {
+        self.warmed_up.call_once(|| {
+            let settings = self.read_settings();
+            // Empty rather than unavailable context values, so the template
+            // is rendered and cached too
+            let env = RenderEnv {
+                detached: false,
+                ..self.render_env(self.clock.now())
+            };
+            render::render(&settings, ContentType::Html, &env);
+            render::widget_html(&settings);
+            css::head_css(&settings);
+        });
+    }
+
+    /// Number of user-perceived characters (grapheme clusters) in the
+    /// current greeting
+    pub fn greeting_grapheme_count(&self) -> usize {
+        self.read_settings().greeting_text.graphemes(true).count()
+    }
+
+    /// Load settings from `store`, keeping the current ones if none are stored
+    pub async fn load_settings(&self, store: &dyn SettingsStore) -> Result<()> {
+        if let Some(json) = store.load(&self.storage_key()).await? {
+            self.apply_stored_settings(parse_stored_settings(&json)?)?;
+        }
+        Ok(())
+    }
+
+    /// Apply the config file, if there is one, and then the environment
+    /// over the current settings
+    fn apply_overrides(&self) -> Result<()> {
+        let layers = override_layers(self.config_file.as_deref())?;
+        self.writer().merge_layers(layers.into_iter().map(|(_, partial)| partial))
+    }
+
+    /// The settings as the overrides leave them: the current settings with
+    /// the config file and then the environment applied over them
+    ///
+    /// These are what the plugin runs with, unless a runtime update has
+    /// since changed an overridden setting; the next activation puts the
+    /// override back. If the overrides can't be read, as when the config
+    /// file has gone, the current settings are returned as they are.
+    pub fn effective_settings(&self) -> HelloWorldSettings {
+        let mut settings = self.settings();
+        for (_, partial) in override_layers(self.config_file.as_deref()).unwrap_or_default() {
+            partial.apply_to(&mut settings);
+        }
+        settings
+    }
+
+    /// Each setting of [`effective_settings`](Self::effective_settings) with
+    /// where its value came from, in name order
+    ///
+    /// The source is `"env"` or `"file"` for overridden settings, `"db"` for
+    /// other settings that differ from their default, whether stored or
+    /// updated at runtime, and `"default"` for the rest.
+    pub fn config_sources(&self) -> Vec<(String, String)> {
+        let layers = override_layers(self.config_file.as_deref()).unwrap_or_default();
+        let mut settings = self.settings();
+        let overridden: Vec<(&str, Vec<&str>)> = layers
+            .into_iter()
+            .map(|(source, partial)| {
+                let fields = partial.set_fields();
+                partial.apply_to(&mut settings);
+                (source, fields)
+            })
+            .collect();
+
+        let effective = serde_json::to_value(settings).expect("settings serialize to JSON");
+        let defaults = serde_json::to_value(HelloWorldSettings::default())
+            .expect("settings serialize to JSON");
+        effective
+            .as_object()
+            .into_iter()
+            .flatten()
+            .map(|(field, value)| {
+                // The last layer to set a field wins
+                let source = overridden
+                    .iter()
+                    .rev()
+                    .find(|(_, fields)| fields.contains(&field.as_str()))
+                    .map(|(source, _)| *source)
+                    .unwrap_or(if defaults.get(field) == Some(value) {
+                        "default"
+                    } else {
+                        "db"
+                    });
+                (field.clone(), source.to_string())
+            })
+            .collect()
+    }
+
+    /// Start reloading the config file on changes, if it's to be watched
+    #[cfg(feature = "native")]
+    fn start_config_watch(&self) {
+        let (Some(path), Some(debounce)) = (&self.config_file, self.config_watch) else {
+            return;
+        };
+
+        let writer = self.writer();
+        let reload_path = path.clone();
+        let reload = move || {
+            // The environment still takes precedence over the file
+            let layers = override_layers(Some(&reload_path));
+            let merged = layers.and_then(|layers| {
+                writer.merge_layers(layers.into_iter().map(|(_, partial)| partial))
+            });
+            if let Err(e) = merged {
+                plugin_log!(
+                    writer.settings.read().log_config(),
+                    WARN,
+                    event = "config_reload_failed",
+                    error = e;
+                    "failed to reload config file, keeping the current settings: {}",
+                    e
+                );
+            }
+        };
+        match watch::ConfigWatcher::start(path, debounce, reload) {
+            Ok(watcher) => *self.config_watcher.lock() = Some(watcher),
+            Err(e) => plugin_log!(
+                self.log_config(),
+                WARN,
+                event = "config_watch_failed",
+                error = e;
+                "failed to watch config file {}: {}",
+                path.display(),
+                e
+            ),
+        }
+    }
+
+    /// The `n` most rendered `[hello]` variants, by normalized attributes
+    /// such as `format="text"`, with their render counts
+    pub fn top_variants(&self, n: usize) -> Vec<(String, u64)> {
+        self.usage.top(n)
+    }
+
+    /// Key the `[hello]` render counts are stored under
+    fn usage_key(&self) -> String {
+        format!("{}:usage", self.storage_key())
+    }
+
+    /// Add the render counts stored in the context's store to this run's
+    async fn load_usage(&self, ctx: &AppContext) -> Result<()> {
+        if let Some(json) = self.settings_store(ctx).load(&self.usage_key()).await? {
+            self.usage
+                .merge_json(&json)
+                .map_err(|e| Error::storage(format!("invalid stored render counts: {}", e)))?;
+        }
+        Ok(())
+    }
+
+    /// Store the render counts in the context's store
+    async fn save_usage(&self, ctx: &AppContext) -> Result<()> {
+        let store = self.settings_store(ctx);
+        let (key, json) = (self.usage_key(), self.usage.to_json());
+        let attempts = self.read_settings().save_max_attempts;
+        store::with_retry(attempts, || store.save(&key, &json)).await
+    }
+
+    /// Load settings from the context's store on startup, retrying with
+    /// backoff while it fails
+    ///
+    /// A store that stays unavailable leaves the current settings in place
+    /// rather than failing startup.
+    async fn preload_settings(&self, ctx: &AppContext) {
+        let store = self.settings_store(ctx);
+        let key = self.storage_key();
+        let attempts = self.read_settings().load_max_attempts;
+        let result = match store::with_retry(attempts, || store.load(&key)).await {
+            // The overrides take precedence over what was stored
+            Ok(Some(json)) => parse_stored_settings(&json)
+                .and_then(|settings| self.apply_stored_settings(settings))
+                .and_then(|()| self.apply_overrides()),
+            Ok(None) => Ok(()),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            plugin_log!(
+                self.log_config(),
+                WARN,
+                event = "settings_load_failed",
+                error = e;
+                "failed to load settings on startup, keeping the current ones: {}",
+                e
+            );
+        }
+    }
+
+    /// Reload settings from the context's store
+    pub async fn restore_settings(&self, ctx: &AppContext) -> Result<()> {
+        self.load_settings(&*self.settings_store(ctx)).await
+    }
+
+    /// Persist current settings to the context's store
+    pub async fn persist_settings(&self, ctx: &AppContext) -> Result<()> {
+        self.save_settings(&*self.settings_store(ctx)).await
+    }
+
+    /// The store registered in `ctx`, or the in-memory fallback
+    fn settings_store(&self, ctx: &AppContext) -> Arc<dyn SettingsStore> {
+        match store::from_context(ctx) {
+            Some(store) => store,
+            None => self.fallback_store.clone(),
+        }
+    }
+
+    /// Load the settings of several plugin instances in one store round-trip
+    ///
+    /// Instances with no stored settings, or settings that fail to parse,
+    /// are left out of the result.
+    pub async fn load_all_settings(
+        ctx: &AppContext,
+        ids: &[&str],
+    ) -> HashMap<String, HelloWorldSettings> {
+        let Some(store) = store::from_context(ctx) else {
+            return HashMap::new();
+        };
+
+        let stored = match store.load_many(ids).await {
+            Ok(stored) => stored,
+            Err(e) => {
+                tracing::warn!("Failed to load Hello World settings: {}", e);
+                return HashMap::new();
+            }
+        };
+
+        stored
+            .into_iter()
+            .filter_map(|(id, json)| match parse_stored_settings(&json) {
+                Ok(settings) => Some((id, settings)),
+                Err(e) => {
+                    tracing::warn!("Ignoring stored settings for {}: {}", id, e);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Persist current settings to `store`, retrying transient failures
+    /// with exponential backoff
+    pub async fn save_settings(&self, store: &dyn SettingsStore) -> Result<()> {
+        let settings = self.settings();
+        let json = serde_json::to_string(&settings)
+            .map_err(|e| Error::storage(format!("failed to serialize settings: {}", e)))?;
+        let key = self.storage_key();
+
+        store::with_retry(settings.save_max_attempts, || store.save(&key, &json)).await
+    }
+
+    /// Export the current settings, signed if `ctx` holds an
+    /// [`ExportSecret`]
+    pub fn export_settings(&self, ctx: &AppContext) -> Result<SettingsBundle> {
+        let settings = serde_json::to_value(self.settings())
+            .map_err(|e| Error::storage(format!("failed to serialize settings: {}", e)))?;
+        Ok(SettingsBundle::new(
+            ctx,
+            self.info.id.to_string(),
+            self.info.version.to_string(),
+            settings,
+        ))
+    }
+
+    /// Apply exported settings, verifying the bundle's signature, which is
+    /// required when `ctx` holds an [`ExportSecret`]
+    pub fn import_settings(&self, ctx: &AppContext, bundle: &SettingsBundle) -> Result<()> {
+        bundle.verify(ctx)?;
+        let settings = serde_json::from_value(bundle.settings.clone())
+            .map_err(|e| Error::validation(format!("invalid settings in bundle: {}", e)))?;
+        self.update_settings(settings)
+    }
+
+    /// Key the settings are stored under: the plugin id behind the
+    /// configured prefix
+    fn storage_key(&self) -> String {
+        format!("{}{}", self.read_settings().storage_key_prefix, self.info.id)
+    }
+
+    /// Whether the plugin currently has a handler registered on hook `name`
+    pub fn is_hook_registered(&self, name: &str) -> bool {
+        self.registered_hooks.read().iter().any(|hook| hook == name)
+    }
+
+    /// Which hooks the last activation registered handlers on, and which it
+    /// couldn't
+    pub fn registration_report(&self) -> RegistrationReport {
+        self.registration.read().clone()
+    }
+
+    fn log_config(&self) -> LogConfig {
+        self.read_settings().log_config()
+    }
+
+    /// Register shortcodes
+    ///
+    /// `registry` is the lock `hooks` was read from; the `[hello]` filter
+    /// uses it to fire `hello_world_shortcode_rendered`.
+    fn register_shortcodes(
+        &self,
+        hooks: &impl HookSink,
+        registry: Weak<RwLock<HookRegistry>>,
+        ctx: &AppContext,
+    ) -> Result<()> {
+        // [hello] shortcode; the filter receives the raw attribute string
+        let settings = self.settings.clone();
+        let clock = Arc::clone(&self.clock);
+        let remote = self.remote.clone();
+        let render_count = Arc::clone(&self.render_count);
+        let provider = self.provider.clone();
+        let usage = self.usage.clone();
+        let ctx = ctx.clone();
+        let suffix = self.hook_suffix();
+        let rendered_action = hooks::namespaced(SHORTCODE_RENDERED_ACTION, suffix.as_deref());
+        hooks.add_filter("shortcode_hello", Box::new(move |attrs: String| {
+            usage.record(&attrs);
+            let env = RenderEnv {
+                remote_greeting: remote.greeting(),
+                remote_fetched_at: remote.fetched_at(),
+                rotation_step: render_count.fetch_add(1, Ordering::Relaxed),
+                provider: provider.clone(),
+                ..RenderEnv::from_context(&ctx, clock.now())
+            };
+            let output = settings.render_guarded("shortcode_hello", |settings| {
+                render::shortcode(settings, &attrs, &env)
+            });
+
+            // Filters run while the caller holds the registry's read lock.
+            // A plain `read()` would deadlock behind a queued writer, so
+            // re-enter recursively. The reference is weak because the
+            // registry owns this closure.
+            if let Some(registry) = registry.upgrade() {
+                registry.read_recursive().do_action_with(
+                    &rendered_action,
+                    serde_json::json!({ "length": output.len(), "attrs": attrs }),
+                );
+            }
+
+            output
+        }), 10)?;
+
+        // [hello_no_footer] opts the current page out of the content footer
+        let ctx = ctx.clone();
+        hooks.add_filter("shortcode_hello_no_footer", Box::new(move |_attrs: String| {
+            if let Some(request) = ctx.get::<Arc<RequestState>>() {
+                request.suppress_footer();
+            }
+            String::new()
+        }), 10)?;
+
+        // [hello_widget id="..."] renders a configured widget inline
+        let settings = self.settings.clone();
+        hooks.add_filter("shortcode_hello_widget", Box::new(move |attrs: String| {
+            settings.render_guarded("shortcode_hello_widget", |settings| {
+                render::widget_shortcode(settings, &attrs)
+            })
+        }), 10)?;
+
+        // [hello_clocks] lists the current time in each configured timezone
+        let settings = self.settings.clone();
+        let clock = Arc::clone(&self.clock);
+        hooks.add_filter("shortcode_hello_clocks", Box::new(move |_attrs: String| {
+            render::clocks_html(&settings.read(), clock.now())
+        }), 10)?;
+
+        // [hello_last_updated] says how long ago the settings changed
+        let settings = self.settings.clone();
+        let clock = Arc::clone(&self.clock);
+        hooks.add_filter("shortcode_hello_last_updated", Box::new(move |_attrs: String| {
+            render::last_updated_html(&settings.read(), clock.now())
+        }), 10)?;
+
+        // [hello_stats] shows the render count and when the settings changed
+        let settings = self.settings.clone();
+        let clock = Arc::clone(&self.clock);
+        let render_count = Arc::clone(&self.render_count);
+        hooks.add_filter("shortcode_hello_stats", Box::new(move |_attrs: String| {
+            let count = render_count.load(Ordering::Relaxed);
+            render::stats_html(&settings.read(), count, clock.now())
+        }), 10)?;
+        Ok(())
+    }
+
+    /// Register every enabled feature's handlers on `hooks`
+    ///
+    /// Each handler is registered independently: one that fails to register
+    /// is put in the report and the rest are still registered.
+    ///
+    /// The handlers do nothing outside the `active_from`..`active_until`
+    /// window, checked against the clock on every call, nor once the plugin
+    /// is deactivated.
+    fn register_hooks(
+        &self,
+        hooks: &impl HookSink,
+        registry: Weak<RwLock<HookRegistry>>,
+        ctx: &AppContext,
+    ) -> RegistrationReport {
+        let settings = self.settings.clone();
+        let clock = Arc::clone(&self.clock);
+        let attached = Arc::new(AtomicBool::new(true));
+        *self.attached.lock() = Arc::clone(&attached);
+        let gated = Gated::new(hooks, move || {
+            attached.load(Ordering::Relaxed) && settings.read().is_active_at(clock.now())
+        });
+        let recording = Recording::new(&gated);
+        // The recording sink never fails, so neither does this
+        let namespaced = Namespaced::new(&recording, self.hook_suffix());
+        let _ = self.register_features(&namespaced, registry, ctx);
+
+        let report = recording.into_report();
+        *self.registered_hooks.write() = report.registered.clone();
+        *self.registration.write() = report.clone();
+        report
+    }
+
+    fn register_features(
+        &self,
+        hooks: &impl HookSink,
+        registry: Weak<RwLock<HookRegistry>>,
+        ctx: &AppContext,
+    ) -> Result<()> {
+        let (features, head_styles) = {
+            let settings = self.read_settings();
+            (settings.features, css::styles_in_head(&settings))
+        };
+        if features.contains(FeatureSet::SHORTCODES) {
+            self.register_shortcodes(hooks, registry, ctx)?;
+        }
+        if features.contains(FeatureSet::WIDGETS) {
+            self.register_widgets(hooks)?;
+        }
+        if features.contains(FeatureSet::HEAD_CSS) && head_styles {
+            self.add_head_css(hooks, ctx)?;
+        }
+        if features.contains(FeatureSet::CONTENT_FILTER) {
+            self.add_content_filter(hooks, ctx)?;
+        }
+        Ok(())
+    }
+
+    /// Warn if the content filter would share a priority with core's
+    /// content filters; see [`RESERVED_CONTENT_PRIORITIES`]
+    fn warn_on_reserved_priorities(&self) {
+        let settings = self.read_settings();
+        let priority = settings.content_filter_priority;
+        if settings.features.contains(FeatureSet::CONTENT_FILTER)
+            && RESERVED_CONTENT_PRIORITIES.contains(&priority)
+        {
+            plugin_log!(
+                settings.log_config(),
+                WARN,
+                event = "reserved_priority",
+                hook = "the_content",
+                priority = priority;
+                "content_filter_priority {} is reserved for core content filters",
+                priority
+            );
+        }
+    }
+
+    /// Register widgets
+    fn register_widgets(&self, hooks: &impl HookSink) -> Result<()> {
+        // Hello World widget
+        let settings = self.settings.clone();
+        hooks.add_filter("widget_hello_world", Box::new(move |_content: String| {
+            settings.render_guarded("widget_hello_world", render::widget_html)
+        }), 10)?;
+
+        // Configured widgets, looked up on every render so edits apply live.
+        // Settings swapped in without validation may exceed the cap, so
+        // enforce it here too.
+        let ids: Vec<String> = {
+            let settings = self.read_settings();
+            if settings.widgets.len() > MAX_WIDGETS {
+                plugin_log!(
+                    settings.log_config(),
+                    WARN,
+                    event = "widgets_capped",
+                    configured = settings.widgets.len(),
+                    max = MAX_WIDGETS;
+                    "{} widgets configured, registering only the first {}",
+                    settings.widgets.len(),
+                    MAX_WIDGETS
+                );
+            }
+            settings
+                .widgets
+                .iter()
+                .take(MAX_WIDGETS)
+                .map(|widget| widget.id.clone())
+                .collect()
+        };
+        for id in ids {
+            let settings = self.settings.clone();
+            let hook = format!("widget_hello_world_{}", id);
+            let name = hook.clone();
+            hooks.add_filter(&hook, Box::new(move |_content: String| {
+                settings.render_guarded(&name, |settings| {
+                    settings
+                        .widgets
+                        .iter()
+                        .find(|widget| widget.id == id)
+                        .map(|widget| render::configured_widget_html(settings, widget))
+                        .unwrap_or_default()
+                })
+            }), 10)?;
+        }
+        Ok(())
+    }
+
+    /// Add custom CSS to head
+    fn add_head_css(&self, hooks: &impl HookSink, ctx: &AppContext) -> Result<()> {
+        let settings = self.settings.clone();
+        let ctx = ctx.clone();
+        hooks.add_action("wp_head", Box::new(move || {
+            // A fresh `RandomState` is randomly keyed, so every page gets
+            // its own seed
+            let seed = RandomState::new().hash_one(());
+            let request = ctx.get::<Arc<RequestState>>().map(|request| &**request);
+            if let Some(style) = css::head_style(&settings.read(), request, seed) {
+                println!("{}", style);
+            }
+        }), 10)?;
+        Ok(())
+    }
+
+    /// Content filter example
+    fn add_content_filter(&self, hooks: &impl HookSink, ctx: &AppContext) -> Result<()> {
+        let priority = self.read_settings().content_filter_priority;
+        let settings = self.settings.clone();
+        let ctx = ctx.clone();
+        hooks.add_filter("the_content", Box::new(move |content: String| {
+            // Add a small footer to all content, unless the page opted out
+            if context::footer_suppressed(&ctx) {
+                content
+            } else {
+                render::append_footer(&settings.read(), content)
+            }
+        }), priority)?;
+        Ok(())
+    }
+}
+
+impl Default for HelloWorldPlugin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Plugin for HelloWorldPlugin {
+    fn info(&self) -> &PluginInfo {
+        &self.info
+    }
+
+    fn state(&self) -> PluginState {
+        *self.state.read()
+    }
+
+    async fn activate(&self, ctx: &AppContext) -> Result<()> {
+        let _activation = self.activation.lock().await;
+        if self.is_active() {
+            return Ok(());
+        }
+
+        plugin_log!(self.log_config(), INFO, event = "activating"; "Activating Hello World plugin");
+
+        if std::env::var(DISABLED_ENV).as_deref() == Ok("1") {
+            plugin_log!(
+                self.log_config(),
+                WARN,
+                event = "activation_skipped",
+                env = DISABLED_ENV;
+                "Hello World plugin disabled by {}, not activating",
+                DISABLED_ENV
+            );
+            return Ok(());
+        }
+
+        if let Err(e) = self.apply_overrides() {
+            plugin_log!(
+                self.log_config(),
+                ERROR,
+                event = "config_overrides_failed",
+                error = e;
+                "failed to apply config file or environment overrides: {}",
+                e
+            );
+            return Err(e);
+        }
+
+        if let Err(e) = self.refresh_remote_greeting(ctx).await {
+            plugin_log!(
+                self.log_config(),
+                WARN,
+                event = "remote_fetch_failed",
+                error = e;
+                "failed to fetch remote greeting: {}",
+                e
+            );
+        }
+
+        self.warn_on_reserved_priorities();
+
+        // Register with hook system
+        if let Some(hooks) = ctx.get::<Arc<RwLock<HookRegistry>>>() {
+            let registry = hooks.read();
+            #[cfg(not(test))]
+            let sink = &*registry;
+            #[cfg(test)]
+            let refusing = hooks::Refusing::new(&*registry, ctx.get::<hooks::RefusedHooks>());
+            #[cfg(test)]
+            let sink = &refusing;
+            let report = self.register_hooks(sink, Arc::downgrade(&hooks), ctx);
+            for (hook, error) in &report.failed {
+                plugin_log!(
+                    self.log_config(),
+                    WARN,
+                    event = "hook_registration_failed",
+                    hook = hook,
+                    error = error;
+                    "failed to register {}: {}",
+                    hook,
+                    error
+                );
+            }
+            // Partly registered is still useful; nothing registered isn't
+            if report.registered.is_empty() && !report.is_complete() {
+                plugin_log!(
+                    self.log_config(),
+                    ERROR,
+                    event = "activation_failed",
+                    failed = report.failed.len();
+                    "no hooks could be registered, Hello World plugin failed to activate"
+                );
+                self.set_state(PluginState::Failed);
+                return Err(Error::internal(format!(
+                    "failed to register any of {} hooks",
+                    report.failed.len()
+                )));
+            }
+        }
+
+        self.set_state(PluginState::Active);
+        plugin_log!(
+            self.log_config(),
+            INFO,
+            event = "activated";
+            "Hello World plugin activated successfully"
+        );
+
+        Ok(())
+    }
+
+    async fn deactivate(&self, ctx: &AppContext) -> Result<()> {
+        plugin_log!(
+            self.log_config(),
+            INFO,
+            event = "deactivating";
+            "Deactivating Hello World plugin"
+        );
+
+        // Shared hooks like `the_content` can't be removed without taking
+        // other plugins' handlers along, so ours are only switched off there
+        self.attached.lock().store(false, Ordering::Relaxed);
+
+        // The recorded names are already namespaced, so they go to the
+        // registry as they are
+        let registered = std::mem::take(&mut *self.registered_hooks.write());
+        if let Some(hooks) = ctx.get::<Arc<RwLock<HookRegistry>>>() {
+            let registry = hooks.read();
+            for hook in &registered {
+                registry.remove_own_hook(hook);
+            }
+        }
+
+        self.set_state(PluginState::Inactive);
+        plugin_log!(
+            self.log_config(),
+            INFO,
+            event = "deactivated";
+            "Hello World plugin deactivated"
+        );
+
+        Ok(())
+    }
+
+    async fn on_startup(&self, ctx: &AppContext) -> Result<()> {
+        plugin_log!(self.log_config(), DEBUG, event = "startup"; "Hello World plugin startup");
+        self.preload_settings(ctx).await;
+        if let Err(e) = self.load_usage(ctx).await {
+            plugin_log!(
+                self.log_config(),
+                WARN,
+                event = "usage_load_failed",
+                error = e;
+                "failed to load render counts: {}",
+                e
+            );
+        }
+        #[cfg(feature = "native")]
+        self.start_config_watch();
+        self.warmup();
+        Ok(())
+    }
+
+    async fn on_shutdown(&self, ctx: &AppContext) -> Result<()> {
+        plugin_log!(self.log_config(), DEBUG, event = "shutdown"; "Hello World plugin shutdown");
+        #[cfg(feature = "native")]
+        self.config_watcher.lock().take();
+        if let Err(e) = self.save_usage(ctx).await {
+            plugin_log!(
+                self.log_config(),
+                WARN,
+                event = "usage_save_failed",
+                error = e;
+                "failed to save render counts: {}",
+                e
+            );
+        }
+        Ok(())
+    }
+
+    fn config_schema(&self) -> Option<serde_json::Value> {
+        Some(self.config_schema_versioned().1)
+    }
+}
+
+/// Plugin entry point - called by the plugin loader
+pub fn create_plugin() -> Arc<dyn Plugin> {
+    Arc::new(HelloWorldPlugin::new())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+
+    /// A plugin with `settings` applied
+    fn plugin_with(settings: HelloWorldSettings) -> HelloWorldPlugin {
+        let plugin = HelloWorldPlugin::new();
+        plugin.update_settings(settings).unwrap();
+        plugin
+    }
+
+    /// A hook registry and a context holding it
+    fn registry_ctx() -> (Arc<RwLock<HookRegistry>>, AppContext) {
+        let registry = Arc::new(RwLock::new(HookRegistry::new()));
+        let mut ctx = AppContext::new();
+        ctx.insert(Arc::clone(&registry));
+        (registry, ctx)
+    }
+
+    /// Store backed by a fixed map
+    struct MapStore(HashMap<String, String>);
+
+    #[async_trait]
+    impl SettingsStore for MapStore {
+        async fn load(&self, key: &str) -> Result<Option<String>> {
+            Ok(self.0.get(key).cloned())
+        }
+
+        async fn save(&self, _key: &str, _value: &str) -> Result<()> {
+            Ok(())
+        }
+
+        async fn load_many(&self, keys: &[&str]) -> Result<HashMap<String, String>> {
+            Ok(keys
+                .iter()
+                .filter_map(|key| Some((key.to_string(), self.0.get(*key)?.clone())))
+                .collect())
+        }
+    }
+
+    /// Store that fails the first `failures` saves
+    struct FlakyStore {
+        failures: AtomicU32,
+        saved: RwLock<Option<String>>,
+    }
+
+    impl FlakyStore {
+        fn new(failures: u32) -> Self {
+            Self {
+                failures: AtomicU32::new(failures),
+                saved: RwLock::new(None),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl SettingsStore for FlakyStore {
+        async fn load(&self, _key: &str) -> Result<Option<String>> {
+            Ok(self.saved.read().clone())
+        }
+
+        async fn save(&self, _key: &str, value: &str) -> Result<()> {
+            let remaining = self.failures.load(Ordering::SeqCst);
+            if remaining > 0 {
+                self.failures.store(remaining - 1, Ordering::SeqCst);
+                return Err(Error::storage("store unavailable"));
+            }
+            *self.saved.write() = Some(value.to_string());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_plugin_creation() {
+        let plugin = HelloWorldPlugin::new();
+        assert_eq!(plugin.info().id, "hello-world");
+        assert_eq!(plugin.info().name, "Hello World");
+    }
+
+    #[tokio::test]
+    async fn test_with_id_namespaces_copies() {
+        let (registry, ctx) = registry_ctx();
+
+        let first = HelloWorldPlugin::new().with_id("tenant-a");
+        let second = HelloWorldPlugin::new().with_id("tenant-b");
+        assert_eq!(first.info().id, "tenant-a");
+        assert_ne!(first.info().id, second.info().id);
+        assert_ne!(first.storage_key(), second.storage_key());
+
+        first.activate(&ctx).await.unwrap();
+        second.activate(&ctx).await.unwrap();
+        assert!(first.is_hook_registered("shortcode_hello_tenant_a"));
+        assert!(second.is_hook_registered("shortcode_hello_tenant_b"));
+        assert!(!first.is_hook_registered("shortcode_hello"));
+        assert!(first.is_hook_registered("the_content"));
+        assert!(registry.read().has_filter("shortcode_hello_tenant_b"));
+        assert!(!registry.read().has_filter("shortcode_hello"));
+    }
+
+    #[test]
+    fn test_settings() {
+        let plugin = HelloWorldPlugin::new();
+        let settings = plugin.settings();
+        assert_eq!(settings.greeting_text, "Hello, World!");
+        assert_eq!(settings.date_mode, DateMode::Absolute);
+    }
+
+    #[test]
+    fn test_update_settings() {
+        let plugin = HelloWorldPlugin::new();
+        plugin
+            .update_settings(HelloWorldSettings {
+                greeting_text: "Howdy!".to_string(),
+                date_mode: DateMode::Hidden,
+                custom_css: ".test { color: red; }".to_string(),
+                ..Default::default()
+            })
+            .unwrap();
+
+        let settings = plugin.settings();
+        assert_eq!(settings.greeting_text, "Howdy!");
+        assert_eq!(settings.date_mode, DateMode::Hidden);
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn test_log_level_warn_silences_info() {
+        let plugin = plugin_with(HelloWorldSettings {
+            log_level: LogLevel::Warn,
+            ..Default::default()
+        });
+
+        let ctx = AppContext::new();
+        plugin.activate(&ctx).await.unwrap();
+        plugin.deactivate(&ctx).await.unwrap();
+
+        assert!(!logs_contain("Activating Hello World plugin"));
+        assert!(!logs_contain("Hello World plugin deactivated"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_save_settings_retries_transient_failures() {
+        let plugin = HelloWorldPlugin::new();
+        let store = FlakyStore::new(2);
+
+        plugin.save_settings(&store).await.unwrap();
+
+        assert!(store.saved.read().is_some());
+        assert_eq!(store.failures.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_greeting_grapheme_count() {
+        let plugin = plugin_with(HelloWorldSettings {
+            greeting_text: "Hi 🇯🇵".to_string(),
+            ..Default::default()
+        });
+
+        // The flag is two regional-indicator code points but one grapheme
+        assert_eq!(plugin.settings().greeting_text.chars().count(), 5);
+        assert_eq!(plugin.greeting_grapheme_count(), 4);
+    }
+
+    #[test]
+    fn test_render_plain_has_no_markup() {
+        let plugin = plugin_with(HelloWorldSettings {
+            greeting_text: "<b>Hi</b> there".to_string(),
+            ..Default::default()
+        });
+
+        let via_method = plugin.render_plain("");
+        let env = RenderEnv::at(Utc::now());
+        let via_attr = render::shortcode(&plugin.settings(), r#"format="text""#, &env);
+
+        assert!(via_method.starts_with("Hi there"));
+        assert!(via_method.contains("Today is"));
+        assert_eq!(via_method, via_attr);
+        assert!(!via_attr.contains('<') && !via_attr.contains('>'));
+
+        // The method is always text, whatever format the attributes ask for
+        assert!(!plugin.render_plain(r#"format="html""#).contains('<'));
+    }
+
+    #[test]
+    fn test_render_stats_show_render_count() {
+        let plugin = HelloWorldPlugin::new();
+        plugin.render_plain("");
+        plugin.render_plain("");
+        plugin.render_plain("");
+
+        assert_eq!(plugin.render_count(), 3);
+        let stats = plugin.render_stats();
+        assert!(stats.contains("<dd>3</dd>"), "{}", stats);
+        // Showing the stats isn't a render itself
+        assert_eq!(plugin.render_count(), 3);
+    }
+
+    #[test]
+    fn test_gradient_colors() {
+        let plugin = plugin_with(HelloWorldSettings {
+            gradient_start: "#112233".to_string(),
+            gradient_end: "#abc".to_string(),
+            ..Default::default()
+        });
+
+        let css = css::head_css(&plugin.settings());
+        assert!(css.contains("linear-gradient(135deg, #112233 0%, #abc 100%)"));
+
+        let result = plugin.update_settings(HelloWorldSettings {
+            gradient_end: "red".to_string(),
+            ..Default::default()
+        });
+        assert!(result.is_err());
+        assert_eq!(plugin.settings().gradient_end, "#abc");
+    }
+
+    #[test]
+    fn test_custom_template() {
+        let plugin = plugin_with(HelloWorldSettings {
+            template: Some(
+                "<p>{{greeting}} from {{site}}, {{user}}</p><script>x()</script>".to_string(),
+            ),
+            ..Default::default()
+        });
+        let env = RenderEnv {
+            site: "Example".to_string(),
+            user: "Ann".to_string(),
+            ..RenderEnv::at(Utc::now())
+        };
+
+        let output = render::shortcode(&plugin.settings(), "", &env);
+        assert_eq!(output, "<p>Hello, World! from Example, Ann</p>");
+    }
+
+    #[test]
+    fn test_template_syntax_error_rejected() {
+        let plugin = HelloWorldPlugin::new();
+        let result = plugin.update_settings(HelloWorldSettings {
+            template: Some("{{#if greeting}}unclosed".to_string()),
+            ..Default::default()
+        });
+        assert!(result.is_err());
+        assert!(plugin.settings().template.is_none());
+    }
+
+    #[test]
+    fn test_settings_accessors_concurrent() {
+        let plugin = Arc::new(HelloWorldPlugin::new());
+
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                let plugin = Arc::clone(&plugin);
+                std::thread::spawn(move || {
+                    for n in 0..200 {
+                        if i % 2 == 0 {
+                            let greeting = format!("Hello #{}", n);
+                            plugin.settings.write().greeting_text = greeting;
+                        } else {
+                            let _ = plugin.read_settings().greeting_text.len();
+                            let _ = plugin.settings();
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert!(plugin.read_settings().greeting_text.starts_with("Hello #"));
+    }
+
+    #[test]
+    fn test_no_footer_flag() {
+        let content = "<p>Post</p>".to_string();
+        let settings = HelloWorldSettings::default();
+        assert!(render::append_footer(&settings, content.clone()).contains("hello-world-footer"));
+
+        let mut ctx = AppContext::new();
+        let request = Arc::new(RequestState::new());
+        ctx.insert(Arc::clone(&request));
+        assert!(!context::footer_suppressed(&ctx));
+
+        request.suppress_footer();
+        assert!(context::footer_suppressed(&ctx));
+    }
+
+    #[test]
+    fn test_no_dependencies() {
+        let plugin = HelloWorldPlugin::new();
+        assert!(plugin.dependencies().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_load_all_settings() {
+        let stored = ["site-a", "site-b", "site-c"]
+            .into_iter()
+            .map(|id| (id.to_string(), format!(r#"{{"greeting_text": "Hello from {}"}}"#, id)))
+            .collect();
+        let store: Arc<dyn SettingsStore> = Arc::new(MapStore(stored));
+        let mut ctx = AppContext::new();
+        ctx.insert(store);
+
+        let ids = ["site-a", "site-b", "site-c", "missing"];
+        let all = HelloWorldPlugin::load_all_settings(&ctx, &ids).await;
+
+        assert_eq!(all.len(), 3);
+        assert_eq!(all["site-b"].greeting_text, "Hello from site-b");
+        assert_eq!(all["site-c"].date_mode, DateMode::Absolute);
+    }
+
+    #[test]
+    fn test_invalid_date_format_rejected() {
+        let plugin = HelloWorldPlugin::new();
+        let result = plugin.update_settings(HelloWorldSettings {
+            date_format: "%Q %Y".to_string(),
+            ..Default::default()
+        });
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_activate_registers_only_enabled_features() {
+        let plugin = plugin_with(HelloWorldSettings {
+            features: FeatureSet::SHORTCODES,
+            ..Default::default()
+        });
+        let (registry, ctx) = registry_ctx();
+
+        plugin.activate(&ctx).await.unwrap();
+
+        let registry = registry.read();
+        assert!(registry.has_filter("shortcode_hello"));
+        assert!(!registry.has_filter("widget_hello_world"));
+        assert!(!registry.has_action("wp_head"));
+        assert!(!registry.has_filter("the_content"));
+    }
+
+    #[tokio::test]
+    async fn test_load_settings_strips_bom() {
+        let plugin = HelloWorldPlugin::new();
+        let stored = "\u{feff}{\"greeting_text\": \"Bonjour\"}".to_string();
+        let store = MapStore(HashMap::from([("hello-world".to_string(), stored)]));
+
+        plugin.load_settings(&store).await.unwrap();
+
+        assert_eq!(plugin.settings().greeting_text, "Bonjour");
+    }
+
+    #[test]
+    fn test_invalid_widget_class_rejected() {
+        let plugin = HelloWorldPlugin::new();
+        for class in ["two words", "x\"><script>", ""] {
+            let result = plugin.update_settings(HelloWorldSettings {
+                widget_extra_classes: vec!["ok".to_string(), class.to_string()],
+                ..Default::default()
+            });
+            assert!(result.is_err(), "{:?} should be rejected", class);
+        }
+    }
+
+    #[test]
+    fn test_render_for_date() {
+        use chrono::TimeZone;
+
+        let plugin = HelloWorldPlugin::new();
+        let date = Utc.with_ymd_and_hms(2031, 3, 17, 9, 0, 0).unwrap();
+
+        let html = plugin.render_for_date(date, "");
+        assert!(html.contains("Today is March 17, 2031"));
+
+        let text = plugin.render_for_date(date, r#"format="text""#);
+        assert!(text.ends_with("Today is March 17, 2031"));
+    }
+
+    #[test]
+    fn test_signed_bundle_round_trip() {
+        let mut ctx = AppContext::new();
+        ctx.insert(ExportSecret(b"s3cret".to_vec()));
+
+        let source = plugin_with(HelloWorldSettings {
+            greeting_text: "Signed and sealed".to_string(),
+            ..Default::default()
+        });
+        let bundle = source.export_settings(&ctx).unwrap();
+        assert!(bundle.signature.is_some());
+
+        let target = HelloWorldPlugin::new();
+        target.import_settings(&ctx, &bundle).unwrap();
+        assert_eq!(target.settings().greeting_text, "Signed and sealed");
+    }
+
+    #[test]
+    fn test_tampered_bundle_rejected() {
+        let mut ctx = AppContext::new();
+        ctx.insert(ExportSecret(b"s3cret".to_vec()));
+
+        let mut bundle = HelloWorldPlugin::new().export_settings(&ctx).unwrap();
+        bundle.settings["greeting_text"] = serde_json::json!("Tampered");
+
+        let target = HelloWorldPlugin::new();
+        assert!(target.import_settings(&ctx, &bundle).is_err());
+        assert_eq!(target.settings().greeting_text, "Hello, World!");
+
+        // Dropping the signature doesn't get it past the check either
+        bundle.signature = None;
+        assert!(target.import_settings(&ctx, &bundle).is_err());
+        assert_eq!(target.settings().greeting_text, "Hello, World!");
+    }
+
+    #[tokio::test]
+    async fn test_persistence_falls_back_to_memory_store() {
+        let ctx = AppContext::new();
+        let plugin = plugin_with(HelloWorldSettings {
+            greeting_text: "Remember me".to_string(),
+            ..Default::default()
+        });
+
+        plugin.persist_settings(&ctx).await.unwrap();
+        plugin.update_settings(HelloWorldSettings::default()).unwrap();
+        plugin.restore_settings(&ctx).await.unwrap();
+
+        assert_eq!(plugin.settings().greeting_text, "Remember me");
+    }
+
+    #[tokio::test]
+    async fn test_shortcode_rendered_action() {
+        let (registry, ctx) = registry_ctx();
+
+        let seen = Arc::new(AtomicUsize::new(0));
+        let listener = Arc::clone(&seen);
+        registry.read().add_action_with(SHORTCODE_RENDERED_ACTION, move |args: &serde_json::Value| {
+            listener.store(args["length"].as_u64().unwrap() as usize, Ordering::SeqCst);
+        }, 10);
+
+        let plugin = HelloWorldPlugin::new();
+        plugin.activate(&ctx).await.unwrap();
+        let output = registry
+            .read()
+            .apply_filters("shortcode_hello", r#"format="text""#.to_string());
+
+        assert!(output.starts_with("Hello, World!"));
+        assert_eq!(seen.load(Ordering::SeqCst), output.len());
+    }
+
+    #[tokio::test]
+    async fn test_styling_mode_none_skips_head_css() {
+        let plugin = plugin_with(HelloWorldSettings {
+            styling_mode: StylingMode::None,
+            ..Default::default()
+        });
+        let (registry, ctx) = registry_ctx();
+
+        plugin.activate(&ctx).await.unwrap();
+
+        let registry = registry.read();
+        assert!(!registry.has_action("wp_head"));
+        let html = registry.apply_filters("shortcode_hello", String::new());
+        assert!(!html.contains("style="));
+    }
+
+    #[test]
+    fn test_weekday_greeting() {
+        use chrono::TimeZone;
+
+        let settings = HelloWorldSettings {
+            weekday_greetings: HashMap::from([(Weekday::Fri, "Happy Friday!".to_string())]),
+            ..Default::default()
+        };
+        let friday = Utc.with_ymd_and_hms(2024, 1, 5, 12, 0, 0).unwrap();
+        let saturday = Utc.with_ymd_and_hms(2024, 1, 6, 12, 0, 0).unwrap();
+
+        let plugin = HelloWorldPlugin::new().with_clock(Arc::new(FixedClock(friday)));
+        plugin.update_settings(settings.clone()).unwrap();
+        assert!(plugin.render_plain("").starts_with("Happy Friday!"));
+
+        let plugin = HelloWorldPlugin::new().with_clock(Arc::new(FixedClock(saturday)));
+        plugin.update_settings(settings).unwrap();
+        assert!(plugin.render_plain("").starts_with("Hello, World!"));
+    }
+
+    #[tokio::test]
+    async fn test_is_hook_registered() {
+        let plugin = HelloWorldPlugin::new();
+        let (registry, ctx) = registry_ctx();
+
+        assert!(!plugin.is_hook_registered("shortcode_hello"));
+        plugin.activate(&ctx).await.unwrap();
+        assert!(plugin.is_hook_registered("shortcode_hello"));
+        assert!(!plugin.is_hook_registered("shortcode_goodbye"));
+        plugin.deactivate(&ctx).await.unwrap();
+        assert!(!plugin.is_hook_registered("shortcode_hello"));
+        assert!(!registry.read().has_filter("shortcode_hello"));
+
+        // Reactivating registers each handler once, not on top of the old
+        plugin.activate(&ctx).await.unwrap();
+        let before = plugin.render_count();
+        registry
+            .read()
+            .apply_filters("shortcode_hello", String::new());
+        assert_eq!(plugin.render_count(), before + 1);
+    }
+
+    #[tokio::test]
+    async fn test_deactivate_leaves_foreign_handlers() {
+        let (registry, ctx) = registry_ctx();
+        registry
+            .read()
+            .add_filter("the_content", |content: String| content + " [foreign]", 20)
+            .unwrap();
+
+        let plugin = HelloWorldPlugin::new();
+        plugin.activate(&ctx).await.unwrap();
+        let content = registry
+            .read()
+            .apply_filters("the_content", "Post".to_string());
+        assert!(content.contains("Powered by") && content.ends_with(" [foreign]"));
+
+        plugin.deactivate(&ctx).await.unwrap();
+        assert_eq!(
+            registry
+                .read()
+                .apply_filters("the_content", "Post".to_string()),
+            "Post [foreign]"
+        );
+    }
 
     #[test]
-    fn test_plugin_creation() {
+    fn test_with_settings() {
+        let plugin = plugin_with(HelloWorldSettings {
+            greeting_text: "Howdy".to_string(),
+            ..Default::default()
+        });
+        let greeting = plugin.with_settings(|settings| settings.greeting_text.clone());
+        assert_eq!(greeting, "Howdy");
+        assert_eq!(plugin.with_settings(|settings| settings.date_mode), DateMode::Absolute);
+    }
+
+    #[test]
+    fn test_migrate_legacy_greeting_string() {
+        let settings = parse_stored_settings(r#""Howdy""#).unwrap();
+        assert_eq!(settings.greeting_text, "Howdy");
+        assert_eq!(settings.date_format, DEFAULT_DATE_FORMAT);
+        assert_eq!(settings.date_mode, DateMode::Absolute);
+    }
+
+    #[test]
+    fn test_validate_all_reports_every_issue() {
+        let settings = HelloWorldSettings {
+            greeting_text: "  ".to_string(),
+            gradient_end: "purple".to_string(),
+            save_max_attempts: 0,
+            load_max_attempts: 0,
+            remote_stale_after_secs: Some(0),
+            widget_max_chars: Some(0),
+            ..Default::default()
+        };
+        let fields: Vec<_> = settings
+            .validate_all()
+            .into_iter()
+            .map(|issue| issue.field)
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                "greeting_text",
+                "save_max_attempts",
+                "load_max_attempts",
+                "remote_stale_after_secs",
+                "widget_max_chars",
+                "gradient_end",
+            ]
+        );
+        assert!(HelloWorldPlugin::new().update_settings(settings).is_err());
+    }
+
+    struct CountingFetcher(AtomicUsize);
+
+    #[async_trait]
+    impl GreetingFetcher for CountingFetcher {
+        async fn fetch(&self, _url: &str) -> Result<String> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok("Hello from afar".to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_offline_skips_remote_fetch() {
+        let remote = HelloWorldSettings {
+            greeting_source: GreetingSource::Remote {
+                url: "https://example.com/greeting".to_string(),
+            },
+            ..Default::default()
+        };
+        let fetcher = Arc::new(CountingFetcher(AtomicUsize::new(0)));
+        let mut ctx = AppContext::new();
+        ctx.insert(Arc::clone(&fetcher) as Arc<dyn GreetingFetcher>);
+
+        let plugin = plugin_with(HelloWorldSettings {
+            offline: true,
+            ..remote.clone()
+        });
+        plugin.activate(&ctx).await.unwrap();
+        assert_eq!(fetcher.0.load(Ordering::SeqCst), 0);
+        assert!(plugin.render_plain("").starts_with("Hello, World!"));
+
+        let plugin = plugin_with(remote);
+        plugin.activate(&ctx).await.unwrap();
+        assert_eq!(fetcher.0.load(Ordering::SeqCst), 1);
+        assert!(plugin.render_plain("").starts_with("Hello from afar"));
+    }
+
+    #[test]
+    fn test_stale_remote_greeting_degrades_health() {
+        use chrono::TimeZone;
+
+        let fetched_at = Utc.with_ymd_and_hms(2024, 1, 5, 12, 0, 0).unwrap();
+        let settings = HelloWorldSettings {
+            greeting_source: GreetingSource::Remote {
+                url: "https://example.com/greeting".to_string(),
+            },
+            remote_stale_after_secs: Some(3_600),
+            debug_markers: true,
+            ..Default::default()
+        };
+        let at = |now| plugin_with(settings.clone()).with_clock(Arc::new(FixedClock(now)));
+
+        let fresh = at(fetched_at + chrono::Duration::minutes(30));
+        fresh.remote.set("Hello from afar".to_string(), fetched_at);
+        assert_eq!(fresh.health_check(), HealthStatus::Healthy);
+        assert!(!fresh.render("", ContentType::Html).contains("hello-world-stale"));
+
+        let stale = at(fetched_at + chrono::Duration::hours(2));
+        stale.remote.set("Hello from afar".to_string(), fetched_at);
+        stale.remote.set_error("connection refused".to_string());
+        assert_eq!(
+            stale.health_check(),
+            HealthStatus::Degraded(
+                "remote greeting is stale, last fetched 2 hours ago; \
+                 last refresh failed: connection refused"
+                    .to_string()
+            )
+        );
+        assert!(stale.render("", ContentType::Html).contains("hello-world-stale"));
+    }
+
+    #[test]
+    fn test_forbidden_chars() {
+        let clean = HelloWorldSettings {
+            forbidden_chars: vec!['\u{7}', '💩'],
+            ..Default::default()
+        };
+        assert!(clean.validate().is_ok());
+
+        let dirty = HelloWorldSettings {
+            greeting_text: "Hello 💩".to_string(),
+            ..clean
+        };
+        assert!(dirty.validate().is_err());
+        let issues = dirty.validate_all();
+        assert_eq!(issues[0].field, "greeting_text");
+        assert!(issues[0].message.contains("'💩'"));
+    }
+
+    #[test]
+    fn test_schema_examples_are_valid() {
+        let schema = HelloWorldPlugin::new().config_schema().unwrap();
+        let properties = schema["properties"].as_object().unwrap();
+        let examples = schema["examples"].as_array().unwrap();
+        assert!(examples.len() >= 2);
+
+        for example in examples {
+            for (key, value) in example.as_object().unwrap() {
+                let property = properties
+                    .get(key)
+                    .unwrap_or_else(|| panic!("{} missing from schema", key));
+                let matches_type = match property["type"].as_str() {
+                    Some("string") => value.is_string(),
+                    Some("boolean") => value.is_boolean(),
+                    Some("integer") => value.is_u64() || value.is_i64(),
+                    Some("array") => value.is_array(),
+                    Some("object") => value.is_object(),
+                    _ => true,
+                };
+                assert!(matches_type, "{} = {} doesn't match the schema", key, value);
+            }
+
+            let settings: HelloWorldSettings = serde_json::from_value(example.clone()).unwrap();
+            assert!(settings.validate().is_ok());
+        }
+    }
+
+    #[test]
+    fn test_warmup_caches_template() {
+        let template = "<p>{{greeting}}, warmed up</p>";
+        let plugin = plugin_with(HelloWorldSettings {
+            template: Some(template.to_string()),
+            ..Default::default()
+        });
+
+        assert!(!template::is_cached(template));
+        plugin.warmup();
+        assert!(template::is_cached(template));
+        assert!(!plugin.is_hook_registered("shortcode_hello"));
+    }
+
+    #[test]
+    fn test_non_http_footer_url_rejected() {
         let plugin = HelloWorldPlugin::new();
-        assert_eq!(plugin.info().id, "hello-world");
-        assert_eq!(plugin.info().name, "Hello World");
+        let result = plugin.update_settings(HelloWorldSettings {
+            footer_url: Some("javascript:alert(1)".to_string()),
+            ..Default::default()
+        });
+        assert!(result.is_err());
     }
 
     #[test]
-    fn test_settings() {
+    fn test_unknown_aria_live_rejected() {
+        let settings = HelloWorldSettings {
+            aria_live: Some("rude".to_string()),
+            ..Default::default()
+        };
+        let issues = settings.validate_all();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].field, "aria_live");
+    }
+
+    #[test]
+    fn test_too_many_widgets_rejected() {
+        let widgets = |count: usize| -> Vec<WidgetConfig> {
+            (0..count)
+                .map(|i| WidgetConfig {
+                    id: format!("w{}", i),
+                    title: "Widget".to_string(),
+                    text: None,
+                })
+                .collect()
+        };
+        let settings = HelloWorldSettings {
+            widgets: widgets(MAX_WIDGETS),
+            ..Default::default()
+        };
+        assert!(settings.validate().is_ok());
+
+        let settings = HelloWorldSettings {
+            widgets: widgets(MAX_WIDGETS + 1),
+            ..Default::default()
+        };
+        let issues = settings.validate_all();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].field, "widgets");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_concurrent_activation_registers_once() {
+        let (registry, ctx) = registry_ctx();
+
+        let renders = Arc::new(AtomicUsize::new(0));
+        let listener = Arc::clone(&renders);
+        registry.read().add_action_with(
+            SHORTCODE_RENDERED_ACTION,
+            move |_args: &serde_json::Value| {
+                listener.fetch_add(1, Ordering::SeqCst);
+            },
+            10,
+        );
+
+        let plugin = Arc::new(HelloWorldPlugin::new());
+        let (first, second) = tokio::join!(
+            tokio::spawn({
+                let (plugin, ctx) = (Arc::clone(&plugin), ctx.clone());
+                async move { plugin.activate(&ctx).await }
+            }),
+            tokio::spawn({
+                let (plugin, ctx) = (Arc::clone(&plugin), ctx.clone());
+                async move { plugin.activate(&ctx).await }
+            }),
+        );
+        first.unwrap().unwrap();
+        second.unwrap().unwrap();
+
+        // A second registration would fire the action twice per render
+        registry.read().apply_filters("shortcode_hello", String::new());
+        assert_eq!(renders.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_settings_fingerprint() {
+        let weekday_greetings = HashMap::from([
+            (Weekday::Mon, "Happy Monday".to_string()),
+            (Weekday::Fri, "Happy Friday".to_string()),
+        ]);
+        let a = plugin_with(HelloWorldSettings {
+            weekday_greetings: weekday_greetings.clone(),
+            ..Default::default()
+        });
+        let b = plugin_with(HelloWorldSettings {
+            weekday_greetings,
+            ..Default::default()
+        });
+        assert_eq!(a.settings_fingerprint(), b.settings_fingerprint());
+
+        let before = a.settings_fingerprint();
+        a.update_settings(HelloWorldSettings {
+            greeting_text: "Howdy".to_string(),
+            ..a.settings()
+        })
+        .unwrap();
+        assert_ne!(a.settings_fingerprint(), before);
+    }
+
+    #[test]
+    fn test_show_date_maps_to_date_mode() {
+        let settings = parse_stored_settings(r#"{"show_date": true}"#).unwrap();
+        assert_eq!(settings.date_mode, DateMode::Absolute);
+
+        let settings = parse_stored_settings(r#"{"show_date": false}"#).unwrap();
+        assert_eq!(settings.date_mode, DateMode::Hidden);
+
+        let settings = parse_stored_settings(r#"{"date_mode": "hidden"}"#).unwrap();
+        assert_eq!(settings.date_mode, DateMode::Hidden);
+    }
+
+    #[tokio::test]
+    async fn test_site_attribute_appends_site_name() {
+        let (registry, mut ctx) = registry_ctx();
+        ctx.insert(SiteName("My Blog".to_string()));
+
+        let plugin = HelloWorldPlugin::new();
+        plugin.activate(&ctx).await.unwrap();
+
+        let registry = registry.read();
+        let html = registry.apply_filters("shortcode_hello", r#"site="true""#.to_string());
+        assert!(html.contains("Hello, World! from My Blog"));
+        let html = registry.apply_filters("shortcode_hello", String::new());
+        assert!(!html.contains("from My Blog"));
+    }
+
+    #[tokio::test]
+    async fn test_storage_key_prefix() {
+        let store = MemoryStore::default();
+        let settings = HelloWorldSettings {
+            greeting_text: "Hello, tenant".to_string(),
+            storage_key_prefix: "tenant-42/".to_string(),
+            ..Default::default()
+        };
+
+        plugin_with(settings.clone()).save_settings(&store).await.unwrap();
+        assert!(store.load("tenant-42/hello-world").await.unwrap().is_some());
+        assert!(store.load("hello-world").await.unwrap().is_none());
+
+        let plugin = plugin_with(HelloWorldSettings {
+            storage_key_prefix: "tenant-42/".to_string(),
+            ..Default::default()
+        });
+        plugin.load_settings(&store).await.unwrap();
+        assert_eq!(plugin.settings().greeting_text, "Hello, tenant");
+    }
+
+    #[test]
+    fn test_sequential_greeting_series_wraps() {
+        let plugin = plugin_with(HelloWorldSettings {
+            greetings: vec!["Hi".to_string(), "Hey".to_string(), "Howdy".to_string()],
+            rotation: Rotation::Sequential,
+            date_mode: DateMode::Hidden,
+            ..Default::default()
+        });
+
+        let series = plugin.render_greeting_series(5, false);
+        let expected = ["Hi", "Hey", "Howdy", "Hi", "Hey"];
+        assert_eq!(series.len(), expected.len());
+        for (html, greeting) in series.iter().zip(expected) {
+            assert!(html.contains(&format!(">{}</div>", greeting)), "{}", html);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_state_observer() {
         let plugin = HelloWorldPlugin::new();
+        let seen = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let observer = Arc::clone(&seen);
+        plugin.on_state_change(move |state| observer.lock().push(state));
+
+        let ctx = AppContext::new();
+        assert!(!plugin.is_active());
+        plugin.activate(&ctx).await.unwrap();
+        assert!(plugin.is_active());
+        plugin.deactivate(&ctx).await.unwrap();
+
+        let seen = seen.lock();
+        assert_eq!(seen.len(), 2);
+        assert!(matches!(seen[0], PluginState::Active));
+        assert!(matches!(seen[1], PluginState::Inactive));
+    }
+
+    #[test]
+    fn test_merge_settings() {
+        let plugin = plugin_with(HelloWorldSettings {
+            custom_css: ".greeting { color: red; }".to_string(),
+            date_mode: DateMode::Hidden,
+            ..Default::default()
+        });
+        plugin
+            .merge_settings(PartialHelloWorldSettings {
+                greeting_text: Some("Howdy".to_string()),
+                ..Default::default()
+            })
+            .unwrap();
+
         let settings = plugin.settings();
-        assert_eq!(settings.greeting_text, "Hello, World!");
-        assert!(settings.show_date);
+        assert_eq!(settings.greeting_text, "Howdy");
+        assert_eq!(settings.custom_css, ".greeting { color: red; }");
+        assert_eq!(settings.date_mode, DateMode::Hidden);
+
+        let invalid = PartialHelloWorldSettings {
+            gradient_start: Some("red".to_string()),
+            ..Default::default()
+        };
+        assert!(plugin.merge_settings(invalid).is_err());
+        assert_eq!(plugin.settings().gradient_start, css::DEFAULT_GRADIENT_START);
     }
 
     #[test]
-    fn test_update_settings() {
+    fn test_audit_log_records_actor_and_fields() {
+        let plugin = HelloWorldPlugin::new();
+        plugin
+            .update_settings_as(
+                HelloWorldSettings {
+                    greeting_text: "Howdy".to_string(),
+                    ..Default::default()
+                },
+                Some("admin"),
+            )
+            .unwrap();
+
+        let log = plugin.audit_log();
+        assert_eq!(log.len(), 1);
+        assert_eq!(log[0].actor.as_deref(), Some("admin"));
+        assert_eq!(log[0].changed_fields, ["greeting_text"]);
+    }
+
+    #[test]
+    fn test_update_settings_stamps_last_updated() {
+        use chrono::TimeZone;
+        let now = Utc.with_ymd_and_hms(2024, 1, 5, 12, 0, 0).unwrap();
+        let plugin = HelloWorldPlugin::new().with_clock(Arc::new(FixedClock(now)));
+        plugin.update_settings(HelloWorldSettings::default()).unwrap();
+        assert_eq!(plugin.settings().last_updated, None);
+
+        plugin
+            .update_settings(HelloWorldSettings {
+                greeting_text: "Howdy".to_string(),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(plugin.settings().last_updated, Some(now));
+
+        // A later update built from the defaults doesn't reset it, nor is
+        // the stamp itself audited as a change
+        plugin
+            .update_settings(HelloWorldSettings {
+                greeting_text: "Howdy again".to_string(),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(plugin.settings().last_updated, Some(now));
+        let log = plugin.audit_log();
+        assert_eq!(log.last().unwrap().changed_fields, ["greeting_text"]);
+    }
+
+    /// Registers into a list, refusing hook `fail_on`
+    struct FailingSink {
+        fail_on: &'static str,
+        registered: parking_lot::Mutex<Vec<String>>,
+    }
+
+    impl FailingSink {
+        fn register(&self, name: &str) -> Result<()> {
+            if name == self.fail_on {
+                return Err(Error::validation(format!("hook {} already registered", name)));
+            }
+            self.registered.lock().push(name.to_string());
+            Ok(())
+        }
+    }
+
+    impl HookSink for FailingSink {
+        fn add_filter(&self, name: &str, _callback: hooks::FilterFn, _priority: i32) -> Result<()> {
+            self.register(name)
+        }
+
+        fn add_action(&self, name: &str, _callback: hooks::ActionFn, _priority: i32) -> Result<()> {
+            self.register(name)
+        }
+
+        fn remove_own_hook(&self, name: &str) {
+            self.registered.lock().retain(|hook| hook != name);
+        }
+    }
+
+    #[test]
+    fn test_failed_registration_is_isolated() {
+        let sink = FailingSink {
+            fail_on: "widget_hello_world",
+            registered: Default::default(),
+        };
+        let plugin = HelloWorldPlugin::new();
+        let report = plugin.register_hooks(&sink, Weak::new(), &AppContext::new());
+
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].0, "widget_hello_world");
+        assert!(!plugin.is_hook_registered("widget_hello_world"));
+        for hook in ["shortcode_hello", "shortcode_hello_widget", "the_content"] {
+            assert!(plugin.is_hook_registered(hook));
+            assert!(sink.registered.lock().iter().any(|name| name == hook));
+        }
+        assert_eq!(plugin.registration_report(), report);
+    }
+
+    #[tokio::test]
+    async fn test_activate_with_a_refused_hook() {
+        let (registry, mut ctx) = registry_ctx();
+        ctx.insert(hooks::RefusedHooks(|name| name == "widget_hello_world"));
+
+        // Partly registered still activates, without the refused hook
+        let plugin = HelloWorldPlugin::new();
+        plugin.activate(&ctx).await.unwrap();
+        assert!(matches!(plugin.state(), PluginState::Active));
+        assert_eq!(
+            plugin.registration_report().failed[0].0,
+            "widget_hello_world"
+        );
+        assert!(!registry.read().has_filter("widget_hello_world"));
+        assert!(registry.read().has_filter("shortcode_hello"));
+    }
+
+    #[tokio::test]
+    async fn test_activate_fails_when_every_hook_is_refused() {
+        let (registry, mut ctx) = registry_ctx();
+        ctx.insert(hooks::RefusedHooks(|_| true));
+
+        let plugin = HelloWorldPlugin::new();
+        assert!(plugin.activate(&ctx).await.is_err());
+        assert!(matches!(plugin.state(), PluginState::Failed));
+        let registry = registry.read();
+        for hook in ["shortcode_hello", "widget_hello_world", "the_content"] {
+            assert!(!registry.has_filter(hook));
+            assert!(!plugin.is_hook_registered(hook));
+        }
+        assert!(!registry.has_action("wp_head"));
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn test_compact_logs_emit_fields() {
+        let plugin = plugin_with(HelloWorldSettings {
+            compact_logs: true,
+            ..Default::default()
+        });
+
+        let ctx = AppContext::new();
+        plugin.activate(&ctx).await.unwrap();
+
+        assert!(logs_contain("event=activating"));
+        assert!(logs_contain("event=activated"));
+        assert!(!logs_contain("Activating Hello World plugin"));
+    }
+
+    #[test]
+    fn test_config_schema_versioned() {
+        let plugin = HelloWorldPlugin::new();
+        let (version, schema) = plugin.config_schema_versioned();
+        assert!(version > 0);
+        assert!(schema.is_object());
+        assert_eq!(plugin.config_schema(), Some(schema));
+    }
+
+    #[test]
+    fn test_schema_version_tracks_settings() {
+        // Adding, removing or renaming a setting changes the fingerprint;
+        // bump SCHEMA_VERSION and record both here together
+        const PINNED: (u32, u64) = (20, 0x5e67_04b1_cb49_34f7);
+
+        let (version, schema) = HelloWorldPlugin::new().config_schema_versioned();
+        let mut names: Vec<&str> = schema["properties"]
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        names.sort_unstable();
+        let fingerprint = fnv1a(names.join("\n").as_bytes());
+        assert_eq!(
+            (version, fingerprint),
+            PINNED,
+            "settings changed: bump SCHEMA_VERSION and update PINNED to ({}, {:#x})",
+            version + 1,
+            fingerprint
+        );
+    }
+
+    #[test]
+    fn test_greeting_series_dedupes_adjacent() {
+        let greetings = ["A", "A", "B", "B", "A"];
+        let plugin = plugin_with(HelloWorldSettings {
+            greetings: greetings.iter().map(|g| g.to_string()).collect(),
+            rotation: Rotation::Sequential,
+            date_mode: DateMode::Hidden,
+            ..Default::default()
+        });
+
+        let series = plugin.render_greeting_series(5, true);
+        let expected = ["A", "B", "A"];
+        assert_eq!(series.len(), expected.len());
+        for (html, greeting) in series.iter().zip(expected) {
+            assert!(html.contains(&format!(">{}</div>", greeting)), "{}", html);
+        }
+    }
+
+    struct FixedProvider;
+
+    #[async_trait]
+    impl GreetingProvider for FixedProvider {
+        async fn greeting(&self, ctx: &ProviderContext<'_>) -> Result<String> {
+            Ok(format!("Greetings, {}", ctx.user()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_greeting_provider() {
+        let (registry, mut ctx) = registry_ctx();
+        ctx.insert(CurrentUser("Ann".to_string()));
+
+        let plugin = HelloWorldPlugin::new().with_greeting_provider(Arc::new(FixedProvider));
+        plugin.activate(&ctx).await.unwrap();
+
+        let html = registry
+            .read()
+            .apply_filters("shortcode_hello", String::new());
+        assert!(html.contains(">Greetings, Ann</div>"), "{}", html);
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn test_reserved_content_priority_warns() {
+        let plugin = plugin_with(HelloWorldSettings {
+            content_filter_priority: 10,
+            ..Default::default()
+        });
+        plugin.activate(&AppContext::new()).await.unwrap();
+        assert!(logs_contain("content_filter_priority 10 is reserved"));
+    }
+
+    /// Store whose saves wait until released
+    #[derive(Default)]
+    struct SlowStore {
+        release: tokio::sync::Notify,
+    }
+
+    #[async_trait]
+    impl SettingsStore for SlowStore {
+        async fn load(&self, _key: &str) -> Result<Option<String>> {
+            Ok(None)
+        }
+
+        async fn save(&self, _key: &str, _value: &str) -> Result<()> {
+            self.release.notified().await;
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_slow_save_does_not_block_renders() {
+        let plugin = HelloWorldPlugin::new();
+        let store = SlowStore::default();
+        let save = plugin.save_settings(&store);
+        tokio::pin!(save);
+        assert!(futures::poll!(&mut save).is_pending());
+
+        // The save is in flight; reads and updates go through regardless
+        assert!(plugin.render_plain("").starts_with("Hello, World!"));
+        plugin
+            .update_settings(HelloWorldSettings {
+                greeting_text: "Howdy".to_string(),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(plugin.settings().greeting_text, "Howdy");
+
+        store.release.notify_one();
+        save.await.unwrap();
+    }
+
+    /// Store whose first `failures` loads fail
+    struct FlakyLoadStore {
+        failures: AtomicU32,
+        stored: String,
+    }
+
+    #[async_trait]
+    impl SettingsStore for FlakyLoadStore {
+        async fn load(&self, _key: &str) -> Result<Option<String>> {
+            if self.failures.load(Ordering::SeqCst) > 0 {
+                self.failures.fetch_sub(1, Ordering::SeqCst);
+                return Err(Error::storage("store unavailable"));
+            }
+            Ok(Some(self.stored.clone()))
+        }
+
+        async fn save(&self, _key: &str, _value: &str) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_startup_retries_settings_load() {
+        let store: Arc<dyn SettingsStore> = Arc::new(FlakyLoadStore {
+            failures: AtomicU32::new(1),
+            stored: r#"{"greeting_text": "Back again"}"#.to_string(),
+        });
+        let mut ctx = AppContext::new();
+        ctx.insert(store);
+
+        let plugin = HelloWorldPlugin::new();
+        plugin.on_startup(&ctx).await.unwrap();
+        assert_eq!(plugin.settings().greeting_text, "Back again");
+    }
+
+    #[tokio::test]
+    async fn test_top_variants() {
+        let (registry, mut ctx) = registry_ctx();
+        let store: Arc<dyn SettingsStore> = Arc::new(MemoryStore::default());
+        ctx.insert(store);
+
         let plugin = HelloWorldPlugin::new();
-        plugin.update_settings(HelloWorldSettings {
-            greeting_text: "Howdy!".to_string(),
-            show_date: false,
-            custom_css: ".test { color: red; }".to_string(),
+        plugin.activate(&ctx).await.unwrap();
+        for attrs in [r#"format="text""#, "", "format=text", r#"format='text'"#, ""] {
+            registry
+                .read()
+                .apply_filters("shortcode_hello", attrs.to_string());
+        }
+
+        assert_eq!(
+            plugin.top_variants(5),
+            [(r#"format="text""#.to_string(), 3), (String::new(), 2)]
+        );
+        assert_eq!(plugin.top_variants(1).len(), 1);
+
+        // Counts survive a restart through the store
+        plugin.on_shutdown(&ctx).await.unwrap();
+        let restarted = HelloWorldPlugin::new();
+        restarted.on_startup(&ctx).await.unwrap();
+        assert_eq!(restarted.top_variants(5), plugin.top_variants(5));
+    }
+
+    struct PanickingProvider;
+
+    #[async_trait]
+    impl GreetingProvider for PanickingProvider {
+        async fn greeting(&self, _ctx: &ProviderContext<'_>) -> Result<String> {
+            panic!("provider bug");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_render_panic_shows_fallback() {
+        let (registry, ctx) = registry_ctx();
+
+        let plugin = plugin_with(HelloWorldSettings {
+            catch_panics: true,
+            ..Default::default()
+        })
+        .with_greeting_provider(Arc::new(PanickingProvider));
+        plugin.activate(&ctx).await.unwrap();
+
+        let html = registry
+            .read()
+            .apply_filters("shortcode_hello", String::new());
+        assert_eq!(html, r#"<div class="hello-world-greeting">Hello, World!</div>"#);
+    }
+
+    #[tokio::test]
+    async fn test_amp_mode_leaves_no_style_attributes() {
+        let (registry, ctx) = registry_ctx();
+
+        let plugin = plugin_with(HelloWorldSettings {
+            amp_mode: true,
+            styling_mode: StylingMode::InlineStyle,
+            template: Some(r#"<p style="color: red">{{greeting}}</p>"#.to_string()),
+            ..Default::default()
         });
+        plugin.activate(&ctx).await.unwrap();
+
+        let registry = registry.read();
+        let outputs = [
+            registry.apply_filters("shortcode_hello", String::new()),
+            registry.apply_filters("widget_hello_world", String::new()),
+            registry.apply_filters("the_content", "<p>Post</p>".to_string()),
+        ];
+        for output in &outputs {
+            assert!(!output.is_empty());
+            assert!(!output.contains("style="), "{}", output);
+        }
+        assert!(plugin.is_hook_registered("wp_head"));
+        assert!(plugin.amp_css().contains(".hello-world-footer {"));
+    }
+
+    #[test]
+    fn test_custom_css_over_budget_needs_stylesheet_url() {
+        let mut settings = HelloWorldSettings {
+            custom_css: ".hello-world-greeting { color: red; }".to_string(),
+            max_inline_css_bytes: Some(16),
+            ..Default::default()
+        };
+        let issues = settings.validate_all();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].field, "custom_css");
+
+        settings.stylesheet_url = Some("https://example.com/hello-world.css".to_string());
+        assert!(settings.validate_all().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_config_file_overrides_stored_settings() {
+        let path = std::env::temp_dir().join(format!(
+            "hello-world-config-{}.toml",
+            std::process::id()
+        ));
+        std::fs::write(&path, "greeting_text = \"Hello from TOML!\"\n").unwrap();
+
+        let store: Arc<dyn SettingsStore> = Arc::new(MemoryStore::default());
+        let mut ctx = AppContext::new();
+        ctx.insert(Arc::clone(&store));
+        plugin_with(HelloWorldSettings {
+            greeting_text: "Stored greeting".to_string(),
+            responsive: true,
+            ..Default::default()
+        })
+        .save_settings(&*store)
+        .await
+        .unwrap();
+
+        let plugin = HelloWorldPlugin::new().with_config_file(&path);
+        plugin.activate(&ctx).await.unwrap();
+        plugin.on_startup(&ctx).await.unwrap();
+        std::fs::remove_file(&path).unwrap();
 
         let settings = plugin.settings();
-        assert_eq!(settings.greeting_text, "Howdy!");
-        assert!(!settings.show_date);
+        assert_eq!(settings.greeting_text, "Hello from TOML!");
+        assert!(settings.responsive);
+        assert!(plugin.render_plain("").starts_with("Hello from TOML!"));
+    }
+
+    #[cfg(feature = "native")]
+    #[tokio::test]
+    async fn test_config_watch_reloads_changed_file() {
+        let dir = std::env::temp_dir().join(format!("hello-world-watch-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("settings.toml");
+        std::fs::write(&path, "greeting_text = \"Before\"\n").unwrap();
+
+        let plugin = HelloWorldPlugin::new()
+            .with_config_file(&path)
+            .with_config_watch(Duration::from_millis(50));
+        let ctx = AppContext::new();
+        plugin.activate(&ctx).await.unwrap();
+        plugin.on_startup(&ctx).await.unwrap();
+        assert_eq!(plugin.settings().greeting_text, "Before");
+
+        std::fs::write(&path, "greeting_text = \"After\"\n").unwrap();
+        for _ in 0..100 {
+            if plugin.settings().greeting_text == "After" {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        assert!(plugin.render_plain("").starts_with("After"));
+
+        // Invalid changes are ignored, and nothing is reloaded after shutdown
+        std::fs::write(&path, "greeting_text = \"\"\n").unwrap();
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(plugin.settings().greeting_text, "After");
+        plugin.on_shutdown(&ctx).await.unwrap();
+        std::fs::write(&path, "greeting_text = \"Too late\"\n").unwrap();
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(plugin.settings().greeting_text, "After");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_hooks_do_nothing_outside_active_window() {
+        use chrono::TimeZone;
+        let from = Utc.with_ymd_and_hms(2026, 12, 1, 0, 0, 0).unwrap();
+        let until = Utc.with_ymd_and_hms(2027, 1, 1, 0, 0, 0).unwrap();
+        let settings = HelloWorldSettings {
+            active_from: Some(from),
+            active_until: Some(until),
+            ..Default::default()
+        };
+
+        let shortcode_at = |now: DateTime<Utc>| {
+            let settings = settings.clone();
+            async move {
+                let (registry, ctx) = registry_ctx();
+                let plugin = plugin_with(settings).with_clock(Arc::new(FixedClock(now)));
+                plugin.activate(&ctx).await.unwrap();
+
+                let registry = registry.read();
+                let content = registry.apply_filters("the_content", "Post".to_string());
+                (registry.apply_filters("shortcode_hello", String::new()), content)
+            }
+        };
+
+        let (html, content) = shortcode_at(from + chrono::Duration::days(3)).await;
+        assert!(html.contains("Hello, World!"));
+        assert!(content.contains("Powered by"));
+
+        for now in [from - chrono::Duration::seconds(1), until] {
+            let (html, content) = shortcode_at(now).await;
+            assert_eq!(html, "");
+            assert_eq!(content, "Post");
+        }
+    }
+
+    #[test]
+    fn test_active_window_must_not_be_empty() {
+        let now = Utc::now();
+        let settings = HelloWorldSettings {
+            active_from: Some(now),
+            active_until: Some(now),
+            ..Default::default()
+        };
+        assert_eq!(settings.validate_all()[0].field, "active_until");
+    }
+
+    #[test]
+    fn test_openapi_fragment() {
+        let plugin = HelloWorldPlugin::new();
+        let fragment = plugin.openapi_fragment();
+
+        let path = &fragment["paths"]["/plugins/hello-world/settings"];
+        let settings_ref = serde_json::json!("#/components/schemas/HelloWorldSettings");
+        assert_eq!(
+            path["get"]["responses"]["200"]["content"]["application/json"]["schema"]["$ref"],
+            settings_ref
+        );
+        assert_eq!(
+            path["put"]["requestBody"]["content"]["application/json"]["schema"]["$ref"],
+            settings_ref
+        );
+        assert_eq!(
+            fragment["components"]["schemas"]["HelloWorldSettings"],
+            plugin.config_schema_versioned().1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_subscribers_receive_updates() {
+        let plugin = HelloWorldPlugin::new();
+        let mut changes = plugin.subscribe();
+
+        plugin
+            .update_settings(HelloWorldSettings {
+                greeting_text: "Hello, subscribers!".to_string(),
+                ..Default::default()
+            })
+            .unwrap();
+        let received = changes.recv().await.unwrap();
+        assert_eq!(received.greeting_text, "Hello, subscribers!");
+
+        // A receiver that falls behind is told so, then carries on with the
+        // updates still held
+        for n in 0..SETTINGS_CHANNEL_CAPACITY + 2 {
+            plugin
+                .update_settings(HelloWorldSettings {
+                    greeting_text: format!("Hello #{}", n),
+                    ..Default::default()
+                })
+                .unwrap();
+        }
+        assert!(matches!(
+            changes.recv().await,
+            Err(broadcast::error::RecvError::Lagged(2))
+        ));
+        assert_eq!(changes.recv().await.unwrap().greeting_text, "Hello #2");
+    }
+
+    #[test]
+    fn test_template_needs_request_context() {
+        let registry = HookRegistry::new();
+        let plugin = plugin_with(HelloWorldSettings {
+            template: Some("<p>{{greeting}} from {{site}}</p>".to_string()),
+            date_mode: DateMode::Hidden,
+            ..Default::default()
+        });
+
+        // Through the hooks the context is there, even if it has no site
+        plugin.register_hooks(&registry, Weak::new(), &AppContext::new());
+        assert_eq!(
+            registry.apply_filters("shortcode_hello", String::new()),
+            "<p>Hello, World! from </p>"
+        );
+
+        // The plugin's own render methods have no context to fill it with
+        assert_eq!(
+            plugin.render("", ContentType::Html),
+            r#"<div class="hello-world-greeting">Hello, World!</div>"#
+        );
     }
 }
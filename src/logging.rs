@@ -0,0 +1,92 @@
+//! Per-instance log verbosity
+//!
+//! The host owns the `tracing` subscriber, so the plugin cannot install its
+//! own filter. Instead every plugin event goes through [`plugin_log!`], which
+//! checks the instance's configured [`LogLevel`] before emitting.
+//!
+//! With `compact_logs` on, events carry only key=value fields instead of a
+//! prose message, for log aggregators that parse fields.
+
+use serde::{Deserialize, Serialize};
+use tracing::Level;
+
+/// Most verbose level the plugin will emit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    #[default]
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    /// The matching `tracing` level
+    pub fn as_tracing_level(self) -> Level {
+        match self {
+            LogLevel::Error => Level::ERROR,
+            LogLevel::Warn => Level::WARN,
+            LogLevel::Info => Level::INFO,
+            LogLevel::Debug => Level::DEBUG,
+            LogLevel::Trace => Level::TRACE,
+        }
+    }
+
+    /// Whether an event at `level` should be emitted
+    pub fn allows(self, level: Level) -> bool {
+        // `tracing` orders levels by verbosity, TRACE being the greatest
+        level <= self.as_tracing_level()
+    }
+}
+
+/// How an instance emits its events
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct LogConfig {
+    pub level: LogLevel,
+    /// Emit the fields alone rather than the prose message
+    pub compact: bool,
+}
+
+/// Emit a `tracing` event if the plugin's log level allows it
+///
+/// `$config` is a [`LogConfig`] and `$level` one of the `tracing::Level`
+/// constants (`INFO`, `DEBUG`, ...). The fields before the `;` are emitted
+/// in compact mode, displayed; the format arguments after it otherwise.
+/// Every event starts with an `event` field naming it.
+///
+/// ```ignore
+/// plugin_log!(config, WARN, event = "fetch_failed", error = e; "fetch failed: {}", e);
+/// ```
+macro_rules! plugin_log {
+    ($config:expr, $level:ident, $($field:ident = $value:expr),+; $($arg:tt)+) => {{
+        let config: $crate::logging::LogConfig = $config;
+        if config.level.allows(::tracing::Level::$level) {
+            if config.compact {
+                ::tracing::event!(
+                    ::tracing::Level::$level,
+                    $($field = ::tracing::field::display(&$value)),+
+                );
+            } else {
+                ::tracing::event!(::tracing::Level::$level, $($arg)+);
+            }
+        }
+    }};
+}
+
+pub(crate) use plugin_log;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allows() {
+        assert!(LogLevel::Warn.allows(Level::ERROR));
+        assert!(LogLevel::Warn.allows(Level::WARN));
+        assert!(!LogLevel::Warn.allows(Level::INFO));
+        assert!(LogLevel::Debug.allows(Level::INFO));
+        assert!(!LogLevel::Debug.allows(Level::TRACE));
+    }
+}
@@ -0,0 +1,200 @@
+//! Partial settings for layered configuration
+//!
+//! Admin forms often submit only the fields that changed. A
+//! [`PartialHelloWorldSettings`] holds just those; merging it overwrites the
+//! matching fields of the current settings and leaves the rest alone.
+//!
+//! The config file and the `HELLO_WORLD_<FIELD>` environment variables are
+//! read into partial settings too, and layered over the stored ones.
+
+use crate::{
+    DateBackend, DateMode, Direction, FeatureSet, GreetingHtmlMode, GreetingSource,
+    HelloWorldSettings, LogLevel, Rotation, StylingMode, WidgetConfig,
+};
+use chrono::{DateTime, Utc, Weekday};
+use rustpress_core::error::Error;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
+
+macro_rules! partial_settings {
+    ($($(#[$attr:meta])* $field:ident: $ty:ty,)*) => {
+        /// [`HelloWorldSettings`] with every field optional; `None` fields
+        /// are left unchanged by a merge
+        #[derive(Debug, Clone, Default, Deserialize)]
+        #[serde(default)]
+        pub struct PartialHelloWorldSettings {
+            $($(#[$attr])* pub $field: Option<$ty>,)*
+        }
+
+        impl PartialHelloWorldSettings {
+            /// Overwrite the fields of `settings` that are set here
+            pub(crate) fn apply_to(self, settings: &mut HelloWorldSettings) {
+                $(if let Some(value) = self.$field {
+                    settings.$field = value;
+                })*
+            }
+
+            /// Names of the fields that are set here
+            pub(crate) fn set_fields(&self) -> Vec<&'static str> {
+                let mut fields = Vec::new();
+                $(if self.$field.is_some() {
+                    fields.push(stringify!($field));
+                })*
+                fields
+            }
+
+            /// The fields set by environment variables, as looked up by `var`
+            pub(crate) fn from_env(var: impl Fn(&str) -> Option<String>) -> Result<Self, Error> {
+                let mut partial = Self::default();
+                $(let name = env_var(stringify!($field));
+                if let Some(raw) = var(&name) {
+                    partial.$field = Some(parse_env(&raw).map_err(|e| {
+                        Error::validation(format!("invalid {}: {}", name, e))
+                    })?);
+                })*
+                Ok(partial)
+            }
+        }
+    };
+}
+
+partial_settings! {
+    greeting_text: String,
+    default_name: String,
+    #[serde(deserialize_with = "present")]
+    first_visit_greeting: Option<String>,
+    greetings: Vec<String>,
+    rotation: Rotation,
+    greeting_source: GreetingSource,
+    offline: bool,
+    #[serde(deserialize_with = "present")]
+    remote_stale_after_secs: Option<u64>,
+    greeting_html: GreetingHtmlMode,
+    #[serde(alias = "show_date")]
+    date_mode: DateMode,
+    hide_date_weekends: bool,
+    date_inline: bool,
+    greeting_date_separator: String,
+    custom_css: String,
+    css_overrides: HashMap<String, String>,
+    log_level: LogLevel,
+    compact_logs: bool,
+    storage_key_prefix: String,
+    save_max_attempts: u32,
+    load_max_attempts: u32,
+    styling_mode: StylingMode,
+    amp_mode: bool,
+    pretty_html: bool,
+    gradient_start: String,
+    gradient_end: String,
+    enforce_contrast: bool,
+    append_footer: bool,
+    #[serde(deserialize_with = "present")]
+    footer_url: Option<String>,
+    content_filter_priority: i32,
+    forbidden_chars: Vec<char>,
+    responsive: bool,
+    random_gradient: bool,
+    dedupe_head_css: bool,
+    #[serde(deserialize_with = "present")]
+    max_inline_css_bytes: Option<usize>,
+    #[serde(deserialize_with = "present")]
+    stylesheet_url: Option<String>,
+    #[serde(deserialize_with = "present")]
+    aria_live: Option<String>,
+    #[serde(deserialize_with = "present")]
+    direction: Option<Direction>,
+    skip_for_bots: bool,
+    timezone: String,
+    clocks: Vec<(String, String)>,
+    weekday_greetings: HashMap<Weekday, String>,
+    date_format: String,
+    date_ordinals: bool,
+    date_backend: DateBackend,
+    date_locale: String,
+    features: FeatureSet,
+    #[serde(deserialize_with = "present")]
+    active_from: Option<DateTime<Utc>>,
+    #[serde(deserialize_with = "present")]
+    active_until: Option<DateTime<Utc>>,
+    #[serde(deserialize_with = "present")]
+    widget_greeting_text: Option<String>,
+    widget_extra_classes: Vec<String>,
+    #[serde(deserialize_with = "present")]
+    widget_max_chars: Option<usize>,
+    widgets: Vec<WidgetConfig>,
+    strict_widgets: bool,
+    debug_markers: bool,
+    catch_panics: bool,
+    #[serde(deserialize_with = "present")]
+    template: Option<String>,
+    template_timeout_ms: u64,
+}
+
+/// Environment variable overriding `field`, e.g. `HELLO_WORLD_GREETING_TEXT`
+fn env_var(field: &str) -> String {
+    format!("{}{}", crate::ENV_PREFIX, field.to_uppercase())
+}
+
+/// An environment variable's value as JSON, or failing that as a string,
+/// so `greeting_text` needn't be quoted but `responsive=true` is a bool
+fn parse_env<T: DeserializeOwned>(raw: &str) -> serde_json::Result<T> {
+    serde_json::from_str(raw).or_else(|e| serde_json::from_value(raw.into()).map_err(|_| e))
+}
+
+/// For fields that are themselves optional: a present `null` clears the
+/// field, while a missing key leaves it unchanged
+fn present<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_null_clears_optional_field() {
+        let mut settings = HelloWorldSettings {
+            template: Some("{{greeting}}".to_string()),
+            widget_max_chars: Some(10),
+            ..Default::default()
+        };
+        let partial: PartialHelloWorldSettings =
+            serde_json::from_str(r#"{"template": null}"#).unwrap();
+        partial.apply_to(&mut settings);
+
+        assert_eq!(settings.template, None);
+        assert_eq!(settings.widget_max_chars, Some(10));
+    }
+
+    #[test]
+    fn test_from_env_parses_values() {
+        let env = HashMap::from([
+            ("HELLO_WORLD_GREETING_TEXT", "123"),
+            ("HELLO_WORLD_RESPONSIVE", "true"),
+            ("HELLO_WORLD_TEMPLATE", "null"),
+        ]);
+        let partial =
+            PartialHelloWorldSettings::from_env(|name| env.get(name).map(|v| v.to_string()))
+                .unwrap();
+
+        assert_eq!(partial.greeting_text.as_deref(), Some("123"));
+        assert_eq!(partial.responsive, Some(true));
+        assert_eq!(partial.template, Some(None));
+        assert_eq!(
+            partial.set_fields(),
+            ["greeting_text", "responsive", "template"]
+        );
+
+        let bad = HashMap::from([("HELLO_WORLD_SAVE_MAX_ATTEMPTS", "many")]);
+        assert!(
+            PartialHelloWorldSettings::from_env(|name| bad.get(name).map(|v| v.to_string()))
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,122 @@
+//! Pluggable greeting sources
+//!
+//! A [`GreetingProvider`] supplies the greeting a render shows, unless a
+//! weekday greeting overrides it. By default the settings pick one of the
+//! built-in providers; hosts can install their own with
+//! [`HelloWorldPlugin::with_greeting_provider`].
+//!
+//! Renders are synchronous and don't wait: a provider that fails, or whose
+//! future isn't ready when first polled, gets `greeting_text` shown instead.
+//! Providers that do I/O should cache, the way the remote source does.
+//!
+//! [`HelloWorldPlugin::with_greeting_provider`]: crate::HelloWorldPlugin::with_greeting_provider
+
+use crate::remote::GreetingSource;
+use crate::render::RenderEnv;
+use crate::rotation::{self, Rotation};
+use crate::HelloWorldSettings;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::FutureExt;
+use rustpress_core::error::Result;
+
+/// Supplies the greeting for a render
+#[async_trait]
+pub trait GreetingProvider: Send + Sync {
+    /// The greeting to show for the render `ctx` describes
+    async fn greeting(&self, ctx: &ProviderContext<'_>) -> Result<String>;
+}
+
+/// What a [`GreetingProvider`] knows about the render it's asked for
+pub struct ProviderContext<'a> {
+    settings: &'a HelloWorldSettings,
+    env: &'a RenderEnv,
+}
+
+impl<'a> ProviderContext<'a> {
+    pub(crate) fn new(settings: &'a HelloWorldSettings, env: &'a RenderEnv) -> Self {
+        Self { settings, env }
+    }
+
+    /// The plugin's current settings
+    pub fn settings(&self) -> &HelloWorldSettings {
+        self.settings
+    }
+
+    /// Time of the render
+    pub fn now(&self) -> DateTime<Utc> {
+        self.env.now
+    }
+
+    /// Display name of the site, or empty if the host didn't provide one
+    pub fn site(&self) -> &str {
+        &self.env.site
+    }
+
+    /// Display name of the user, or empty if the host didn't provide one
+    pub fn user(&self) -> &str {
+        &self.env.user
+    }
+
+    /// Number of this render, counting from 0
+    pub fn render_number(&self) -> u64 {
+        self.env.rotation_step
+    }
+}
+
+/// Always `greeting_text`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StaticProvider;
+
+#[async_trait]
+impl GreetingProvider for StaticProvider {
+    async fn greeting(&self, ctx: &ProviderContext<'_>) -> Result<String> {
+        Ok(ctx.settings.greeting_text.clone())
+    }
+}
+
+/// The pick of the configured [`Rotation`], or `greeting_text` if there is
+/// nothing to rotate through
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RotationProvider;
+
+#[async_trait]
+impl GreetingProvider for RotationProvider {
+    async fn greeting(&self, ctx: &ProviderContext<'_>) -> Result<String> {
+        Ok(rotation::pick(ctx.settings, ctx.env)
+            .unwrap_or(&ctx.settings.greeting_text)
+            .to_string())
+    }
+}
+
+/// The last fetched remote greeting, or what [`RotationProvider`] gives
+/// until the first fetch succeeds
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RemoteProvider;
+
+#[async_trait]
+impl GreetingProvider for RemoteProvider {
+    async fn greeting(&self, ctx: &ProviderContext<'_>) -> Result<String> {
+        match &ctx.env.remote_greeting {
+            Some(greeting) => Ok(greeting.clone()),
+            None => RotationProvider.greeting(ctx).await,
+        }
+    }
+}
+
+/// The built-in provider `settings` call for
+pub(crate) fn for_settings(settings: &HelloWorldSettings) -> &'static dyn GreetingProvider {
+    match settings.greeting_source {
+        GreetingSource::Remote { .. } => &RemoteProvider,
+        GreetingSource::Local if settings.rotation != Rotation::Fixed => &RotationProvider,
+        GreetingSource::Local => &StaticProvider,
+    }
+}
+
+/// The greeting `provider` gives for `ctx` right away, if it gives one
+pub(crate) fn resolve(
+    provider: &dyn GreetingProvider,
+    ctx: &ProviderContext<'_>,
+) -> Option<Result<String>> {
+    provider.greeting(ctx).now_or_never()
+}
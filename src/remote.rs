@@ -0,0 +1,157 @@
+//! Greeting fetched from a remote URL
+//!
+//! Greetings are fetched through the [`GreetingFetcher`] the host provides in
+//! the [`AppContext`]. Without one, `native` builds use [`HttpFetcher`]; other
+//! targets, such as WASM, can wrap the host's own fetch in a
+//! [`CallbackFetcher`]. Fetched greetings are cached and renders only read the
+//! cache, so a slow or unreachable remote never holds up a page.
+//!
+//! [`AppContext`]: rustpress_core::context::AppContext
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use parking_lot::RwLock;
+use rustpress_core::context::AppContext;
+#[cfg(feature = "native")]
+use rustpress_core::error::Error;
+use rustpress_core::error::Result;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+/// Where the default greeting comes from
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GreetingSource {
+    /// `greeting_text`
+    #[default]
+    Local,
+    /// The body served at `url`, falling back to `greeting_text` until the
+    /// first successful fetch
+    Remote { url: String },
+}
+
+/// HTTP client used to fetch remote greetings
+#[async_trait]
+pub trait GreetingFetcher: Send + Sync {
+    /// Fetch the greeting served at `url`
+    async fn fetch(&self, url: &str) -> Result<String>;
+}
+
+/// Boxed future returned by a [`CallbackFetcher`] callback
+pub type FetchFuture = Pin<Box<dyn Future<Output = Result<String>> + Send>>;
+
+/// A fetcher that hands the URL to a host callback
+pub struct CallbackFetcher(Box<dyn Fn(String) -> FetchFuture + Send + Sync>);
+
+impl CallbackFetcher {
+    pub fn new(fetch: impl Fn(String) -> FetchFuture + Send + Sync + 'static) -> Self {
+        Self(Box::new(fetch))
+    }
+}
+
+#[async_trait]
+impl GreetingFetcher for CallbackFetcher {
+    async fn fetch(&self, url: &str) -> Result<String> {
+        (self.0)(url.to_string()).await
+    }
+}
+
+/// Fetches over HTTP with `reqwest`
+#[cfg(feature = "native")]
+#[derive(Debug, Clone, Default)]
+pub struct HttpFetcher {
+    client: reqwest::Client,
+}
+
+/// How long [`HttpFetcher`] waits for a response
+#[cfg(feature = "native")]
+const FETCH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+#[cfg(feature = "native")]
+#[async_trait]
+impl GreetingFetcher for HttpFetcher {
+    async fn fetch(&self, url: &str) -> Result<String> {
+        let failed = |e: reqwest::Error| Error::internal(format!("fetching {}: {}", url, e));
+        let response = self
+            .client
+            .get(url)
+            .timeout(FETCH_TIMEOUT)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(failed)?;
+        let body = response.text().await.map_err(failed)?;
+        Ok(body.trim().to_string())
+    }
+}
+
+/// The last successfully fetched greeting, shared between the plugin and
+/// its hook closures
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RemoteCache(Arc<RwLock<RemoteState>>);
+
+#[derive(Debug, Default)]
+struct RemoteState {
+    greeting: Option<String>,
+    fetched_at: Option<DateTime<Utc>>,
+    /// Why the last fetch failed, if it did
+    last_error: Option<String>,
+}
+
+impl RemoteCache {
+    /// The cached greeting, if a fetch has succeeded
+    pub fn greeting(&self) -> Option<String> {
+        self.0.read().greeting.clone()
+    }
+
+    /// When the cached greeting was fetched
+    pub fn fetched_at(&self) -> Option<DateTime<Utc>> {
+        self.0.read().fetched_at
+    }
+
+    /// Why the last fetch failed, unless it succeeded
+    pub fn last_error(&self) -> Option<String> {
+        self.0.read().last_error.clone()
+    }
+
+    /// Cache `greeting`, fetched at `at`
+    pub fn set(&self, greeting: String, at: DateTime<Utc>) {
+        *self.0.write() = RemoteState {
+            greeting: Some(greeting),
+            fetched_at: Some(at),
+            last_error: None,
+        };
+    }
+
+    /// Note that a fetch failed, keeping the greeting cached before it
+    pub fn set_error(&self, error: String) {
+        self.0.write().last_error = Some(error);
+    }
+}
+
+/// Whether a greeting fetched at `fetched_at` is more than `stale_after`
+/// seconds old at `now`
+pub(crate) fn is_stale(
+    fetched_at: DateTime<Utc>,
+    stale_after: Option<u64>,
+    now: DateTime<Utc>,
+) -> bool {
+    stale_after.is_some_and(|secs| {
+        (now - fetched_at).num_seconds() > i64::try_from(secs).unwrap_or(i64::MAX)
+    })
+}
+
+/// The fetcher registered in `ctx`, or the built-in HTTP one if there is
+/// none and the `native` feature is on
+pub(crate) fn fetcher_from_context(ctx: &AppContext) -> Option<Arc<dyn GreetingFetcher>> {
+    let registered = ctx
+        .get::<Arc<dyn GreetingFetcher>>()
+        .map(|fetcher| Arc::clone(&*fetcher));
+
+    #[cfg(feature = "native")]
+    let registered = registered.or_else(|| Some(Arc::new(HttpFetcher::default()) as _));
+
+    registered
+}
@@ -0,0 +1,2062 @@
+//! Greeting and widget markup
+//!
+//! Renderers are plain functions over a settings snapshot and a
+//! [`RenderEnv`], so the registered hook closures and the public `render_*`
+//! methods produce identical output.
+
+use crate::attrs::parse_shortcode_attrs;
+use crate::clock;
+use crate::context::{CurrentUser, PostMeta, ReturningVisitor, SiteId, SiteName, UserAgent};
+use crate::css::{self, StylingMode};
+use crate::logging::plugin_log;
+use crate::provider::{self, GreetingProvider, ProviderContext};
+use crate::remote::{self, GreetingSource};
+use crate::template::{self, TemplateVars};
+use crate::{HelloWorldSettings, WidgetConfig};
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Datelike, NaiveDate, Utc, Weekday};
+use rustpress_core::context::AppContext;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Default format of the "Today is ..." line
+pub const DEFAULT_DATE_FORMAT: &str = "%B %d, %Y";
+
+/// Used when the configured format renders nothing
+const ISO_DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Everything a render needs besides the settings
+#[derive(Clone)]
+pub(crate) struct RenderEnv {
+    pub now: DateTime<Utc>,
+    pub site: String,
+    pub site_id: String,
+    pub user: String,
+    /// Metadata of the post being rendered, if any
+    pub post_meta: HashMap<String, String>,
+    /// Whether the visitor has been here before, if the host knows
+    pub returning_visitor: Option<bool>,
+    /// `User-Agent` of the request, or empty if the host didn't provide one
+    pub user_agent: String,
+    /// Last fetched remote greeting, used when the source is remote
+    pub remote_greeting: Option<String>,
+    /// When `remote_greeting` was fetched
+    pub remote_fetched_at: Option<DateTime<Utc>>,
+    /// Follow the greeting with "from <site>", as `[hello site="true"]` does
+    pub append_site: bool,
+    /// Number of this render, for rotating greetings
+    pub rotation_step: u64,
+    /// Host-installed provider, replacing the one the settings select
+    pub provider: Option<Arc<dyn GreetingProvider>>,
+    /// Rendered outside of any request, so the context values above are
+    /// unavailable rather than empty
+    pub detached: bool,
+}
+
+impl RenderEnv {
+    /// An environment with only the render time set
+    pub fn at(now: DateTime<Utc>) -> Self {
+        Self {
+            now,
+            site: String::new(),
+            site_id: String::new(),
+            user: String::new(),
+            post_meta: HashMap::new(),
+            returning_visitor: None,
+            user_agent: String::new(),
+            remote_greeting: None,
+            remote_fetched_at: None,
+            append_site: false,
+            rotation_step: 0,
+            provider: None,
+            detached: false,
+        }
+    }
+
+    /// An environment for rendering outside of a request, at `now`
+    pub fn detached(now: DateTime<Utc>) -> Self {
+        Self {
+            detached: true,
+            ..Self::at(now)
+        }
+    }
+
+    /// Pick up the optional site and user values from `ctx`
+    pub fn from_context(ctx: &AppContext, now: DateTime<Utc>) -> Self {
+        Self {
+            site: ctx
+                .get::<SiteName>()
+                .map(|s| s.0.clone())
+                .unwrap_or_default(),
+            site_id: ctx.get::<SiteId>().map(|s| s.0.clone()).unwrap_or_default(),
+            user: ctx
+                .get::<CurrentUser>()
+                .map(|u| u.0.clone())
+                .unwrap_or_default(),
+            post_meta: ctx
+                .get::<PostMeta>()
+                .map(|meta| meta.0.clone())
+                .unwrap_or_default(),
+            returning_visitor: ctx.get::<ReturningVisitor>().map(|visitor| visitor.0),
+            user_agent: ctx
+                .get::<UserAgent>()
+                .map(|agent| agent.0.clone())
+                .unwrap_or_default(),
+            ..Self::at(now)
+        }
+    }
+}
+
+/// Whether and how the date line is shown
+///
+/// Also deserializes from the `true`/`false` of the deprecated `show_date`
+/// setting, which maps to `Absolute`/`Hidden`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DateMode {
+    /// "Today is <date>"
+    #[default]
+    Absolute,
+    /// No date line
+    Hidden,
+}
+
+impl<'de> Deserialize<'de> for DateMode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "snake_case")]
+        enum Mode {
+            Absolute,
+            Hidden,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            ShowDate(bool),
+            Mode(Mode),
+        }
+
+        Ok(match Repr::deserialize(deserializer)? {
+            Repr::ShowDate(true) | Repr::Mode(Mode::Absolute) => DateMode::Absolute,
+            Repr::ShowDate(false) | Repr::Mode(Mode::Hidden) => DateMode::Hidden,
+        })
+    }
+}
+
+/// Output formats the greeting can be rendered in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ContentType {
+    /// Markup for pages
+    #[default]
+    Html,
+    /// Plain text for feeds and other markup-free contexts
+    Text,
+    /// `{"greeting": ..., "date": ...}` for APIs; `date` is null when hidden
+    Json,
+}
+
+/// Render the `[hello]` shortcode for the raw attribute string `attrs`
+///
+/// `format="text"` and `format="json"` select the non-HTML renderers, and
+/// `site="true"` appends "from <site>" to the greeting.
+pub(crate) fn shortcode(settings: &HelloWorldSettings, attrs: &str, env: &RenderEnv) -> String {
+    let accept = match parse_shortcode_attrs(attrs)
+        .get("format")
+        .map(String::as_str)
+    {
+        Some("text") => ContentType::Text,
+        Some("json") => ContentType::Json,
+        _ => ContentType::Html,
+    };
+    shortcode_as(settings, attrs, accept, env)
+}
+
+/// Render the `[hello]` shortcode as `accept`, whatever its `format`
+/// attribute says
+pub(crate) fn shortcode_as(
+    settings: &HelloWorldSettings,
+    attrs: &str,
+    accept: ContentType,
+    env: &RenderEnv,
+) -> String {
+    let attrs = parse_shortcode_attrs(attrs);
+    if attrs.get("site").map(String::as_str) == Some("true") {
+        let env = RenderEnv {
+            append_site: true,
+            ..env.clone()
+        };
+        render(settings, accept, &env)
+    } else {
+        render(settings, accept, env)
+    }
+}
+
+/// Render the greeting as `accept`
+///
+/// With `skip_for_bots`, crawlers get only the bare greeting as HTML or
+/// text; JSON is left alone, as it's for API clients rather than pages.
+pub(crate) fn render(
+    settings: &HelloWorldSettings,
+    accept: ContentType,
+    env: &RenderEnv,
+) -> String {
+    let lean = settings.skip_for_bots && is_crawler(&env.user_agent);
+    match accept {
+        ContentType::Html if lean => format!(
+            r#"<div class="hello-world-greeting">{}</div>"#,
+            greeting_markup(settings, env)
+        ),
+        ContentType::Text if lean => strip_tags(&current_greeting(settings, env)),
+        ContentType::Html => {
+            let html = greeting_html(settings, env) + &stale_marker(settings, env);
+            with_debug_markers(settings, html)
+        }
+        ContentType::Text => greeting_plain(settings, env),
+        ContentType::Json => greeting_json(settings, env),
+    }
+}
+
+/// With debug markers on, a small "stale" badge for a remote greeting older
+/// than `remote_stale_after_secs`
+fn stale_marker(settings: &HelloWorldSettings, env: &RenderEnv) -> String {
+    let remote = matches!(settings.greeting_source, GreetingSource::Remote { .. });
+    match env.remote_fetched_at {
+        Some(fetched_at)
+            if settings.debug_markers
+                && remote
+                && remote::is_stale(fetched_at, settings.remote_stale_after_secs, env.now) =>
+        {
+            format!(
+                r#"<span class="hello-world-stale" title="fetched {}">stale</span>"#,
+                time_ago(fetched_at, env.now)
+            )
+        }
+        _ => String::new(),
+    }
+}
+
+/// Wrap `html` in `hello-world:start`/`end` comments if debug markers are on
+fn with_debug_markers(settings: &HelloWorldSettings, html: String) -> String {
+    if settings.debug_markers {
+        format!("<!-- hello-world:start -->{}<!-- hello-world:end -->", html)
+    } else {
+        html
+    }
+}
+
+/// Lowercase fragments of the user agents of well-known crawlers
+const CRAWLER_USER_AGENTS: [&str; 12] = [
+    "googlebot",
+    "bingbot",
+    "slurp",
+    "duckduckbot",
+    "baiduspider",
+    "yandexbot",
+    "applebot",
+    "facebookexternalhit",
+    "twitterbot",
+    "linkedinbot",
+    "ahrefsbot",
+    "semrushbot",
+];
+
+/// Whether `user_agent` belongs to one of [`CRAWLER_USER_AGENTS`]
+fn is_crawler(user_agent: &str) -> bool {
+    let user_agent = user_agent.to_ascii_lowercase();
+    CRAWLER_USER_AGENTS
+        .iter()
+        .any(|crawler| user_agent.contains(crawler))
+}
+
+/// Placeholder in greetings for the visitor's name
+const NAME_PLACEHOLDER: &str = "{name}";
+
+/// The greeting to render, with `{name}` filled in and followed by
+/// "from <site>" if `env` asks for it
+pub(crate) fn current_greeting<'a>(
+    settings: &'a HelloWorldSettings,
+    env: &'a RenderEnv,
+) -> Cow<'a, str> {
+    fill_name(
+        unfilled_greeting(settings, env),
+        visitor_name(settings, env),
+    )
+}
+
+/// Name of the visitor, `default_name` for anonymous ones
+fn visitor_name<'a>(settings: &'a HelloWorldSettings, env: &'a RenderEnv) -> &'a str {
+    if env.user.trim().is_empty() {
+        &settings.default_name
+    } else {
+        &env.user
+    }
+}
+
+/// `greeting` with every `{name}` replaced by `name`
+fn fill_name<'a>(greeting: Cow<'a, str>, name: &str) -> Cow<'a, str> {
+    if greeting.contains(NAME_PLACEHOLDER) {
+        Cow::Owned(greeting.replace(NAME_PLACEHOLDER, name))
+    } else {
+        greeting
+    }
+}
+
+/// [`current_greeting`] with `{name}` not yet filled in
+fn unfilled_greeting<'a>(settings: &'a HelloWorldSettings, env: &'a RenderEnv) -> Cow<'a, str> {
+    let greeting = base_greeting(settings, env);
+    if env.append_site && !env.site.is_empty() {
+        Cow::Owned(format!("{} from {}", greeting, env.site))
+    } else {
+        greeting
+    }
+}
+
+/// The configured weekday greeting if there is one for today, then
+/// `first_visit_greeting` for new visitors, otherwise the provider's
+/// greeting, falling back to `greeting_text`
+fn base_greeting<'a>(settings: &'a HelloWorldSettings, env: &'a RenderEnv) -> Cow<'a, str> {
+    let weekday = clock::local_time(settings, env.now).weekday();
+    if let Some(greeting) = settings.weekday_greetings.get(&weekday) {
+        return Cow::Borrowed(greeting);
+    }
+    if let (Some(false), Some(greeting)) = (env.returning_visitor, &settings.first_visit_greeting) {
+        return Cow::Borrowed(greeting);
+    }
+
+    let provider = match &env.provider {
+        Some(provider) => provider.as_ref(),
+        None => provider::for_settings(settings),
+    };
+    match provider::resolve(provider, &ProviderContext::new(settings, env)) {
+        Some(Ok(greeting)) => Cow::Owned(greeting),
+        Some(Err(e)) => {
+            plugin_log!(
+                settings.log_config(),
+                WARN,
+                event = "provider_failed",
+                error = e;
+                "greeting provider failed: {}",
+                e
+            );
+            Cow::Borrowed(&settings.greeting_text)
+        }
+        None => {
+            plugin_log!(
+                settings.log_config(),
+                DEBUG,
+                event = "provider_pending";
+                "greeting provider not ready, using greeting_text"
+            );
+            Cow::Borrowed(&settings.greeting_text)
+        }
+    }
+}
+
+/// What formats the date line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DateBackend {
+    /// chrono, with `date_format` and `date_ordinals`; month and day names
+    /// are English
+    #[default]
+    Chrono,
+    /// ICU's CLDR long date for `date_locale`, e.g. "14 octobre 2026" for
+    /// `fr`. Needs the `icu` feature, without which chrono is used
+    Icu,
+}
+
+/// Text direction of the greeting, as its `dir` attribute
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    /// Taken from the first strong character, for greetings that may be in
+    /// either direction
+    Auto,
+    /// Left to right
+    Ltr,
+    /// Right to left, e.g. Arabic or Hebrew
+    Rtl,
+}
+
+impl Direction {
+    /// The `dir` attribute value
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Direction::Auto => "auto",
+            Direction::Ltr => "ltr",
+            Direction::Rtl => "rtl",
+        }
+    }
+}
+
+/// ` dir="..."` for the configured direction, or nothing
+fn dir_attr(settings: &HelloWorldSettings) -> String {
+    settings
+        .direction
+        .map(|direction| format!(r#" dir="{}""#, direction.as_str()))
+        .unwrap_or_default()
+}
+
+/// How markup in the greeting is treated in HTML output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GreetingHtmlMode {
+    /// Tags are shown as text
+    Escape,
+    /// `b`, `i`, `em`, `strong` and `br` are kept, without attributes;
+    /// other tags are dropped, scripts and styles along with their content
+    #[default]
+    SafeSubset,
+    /// Markup is output as is; only for greetings set by trusted admins
+    Raw,
+}
+
+/// Tags [`GreetingHtmlMode::SafeSubset`] keeps
+const SAFE_GREETING_TAGS: [&str; 5] = ["b", "i", "em", "strong", "br"];
+
+/// The greeting, with its markup treated as `greeting_html` says
+///
+/// The visitor's name is filled in afterwards and always escaped, since
+/// unlike the greeting it isn't written by an admin.
+fn greeting_markup<'a>(settings: &'a HelloWorldSettings, env: &'a RenderEnv) -> Cow<'a, str> {
+    let markup = apply_greeting_html(settings, unfilled_greeting(settings, env));
+    fill_name(markup, &escape_html(visitor_name(settings, env)))
+}
+
+/// `text` as HTML, with its markup treated as `greeting_html` says
+fn apply_greeting_html<'a>(settings: &HelloWorldSettings, text: Cow<'a, str>) -> Cow<'a, str> {
+    match settings.greeting_html {
+        GreetingHtmlMode::Escape => Cow::Owned(escape_html(&text)),
+        GreetingHtmlMode::SafeSubset => Cow::Owned(safe_subset().clean(&text).to_string()),
+        GreetingHtmlMode::Raw => text,
+    }
+}
+
+/// Sanitizer for [`GreetingHtmlMode::SafeSubset`], built once
+fn safe_subset() -> &'static ammonia::Builder<'static> {
+    static SANITIZER: OnceLock<ammonia::Builder<'static>> = OnceLock::new();
+    SANITIZER.get_or_init(|| {
+        let mut builder = ammonia::Builder::empty();
+        builder
+            .tags(SAFE_GREETING_TAGS.into_iter().collect())
+            .clean_content_tags(["script", "style"].into_iter().collect());
+        builder
+    })
+}
+
+/// Tags a rendered template may use; anything else is dropped, scripts and
+/// styles along with their content
+const TEMPLATE_TAGS: [&str; 18] = [
+    "a", "abbr", "b", "br", "div", "em", "h2", "h3", "h4", "i", "li", "ol", "p", "small", "span",
+    "strong", "time", "ul",
+];
+
+/// Attributes a rendered template may use on any of [`TEMPLATE_TAGS`];
+/// event handlers and `style` are always dropped
+const TEMPLATE_ATTRIBUTES: [&str; 5] = ["class", "dir", "id", "lang", "title"];
+
+/// Sanitizer for rendered templates, built once
+fn template_sanitizer() -> &'static ammonia::Builder<'static> {
+    static SANITIZER: OnceLock<ammonia::Builder<'static>> = OnceLock::new();
+    SANITIZER.get_or_init(|| {
+        let mut builder = ammonia::Builder::empty();
+        builder
+            .tags(TEMPLATE_TAGS.into_iter().collect())
+            .generic_attributes(TEMPLATE_ATTRIBUTES.into_iter().collect())
+            .tag_attributes(
+                [
+                    ("a", ["href"].into_iter().collect()),
+                    ("time", ["datetime"].into_iter().collect()),
+                ]
+                .into_iter()
+                .collect(),
+            )
+            .url_schemes(["http", "https", "mailto"].into_iter().collect())
+            .link_rel(Some("noopener noreferrer"))
+            .clean_content_tags(["script", "style"].into_iter().collect());
+        builder
+    })
+}
+
+/// Whether the date line is shown at `now`
+fn shows_date(settings: &HelloWorldSettings, now: DateTime<Utc>) -> bool {
+    settings.date_mode == DateMode::Absolute
+        && !(settings.hide_date_weekends
+            && matches!(
+                clock::local_time(settings, now).weekday(),
+                Weekday::Sat | Weekday::Sun
+            ))
+}
+
+/// The greeting as HTML, from the user template if one is configured
+///
+/// A template needs the request's context values, even if they're empty; a
+/// detached render shows the built-in greeting rather than a template with
+/// every placeholder blank.
+pub(crate) fn greeting_html(settings: &HelloWorldSettings, env: &RenderEnv) -> String {
+    if settings.template.is_some() && env.detached {
+        plugin_log!(
+            settings.log_config(),
+            DEBUG,
+            event = "template_skipped";
+            "no request context to fill the greeting template, using the built-in greeting"
+        );
+    } else if let Some(tpl) = &settings.template {
+        let vars = TemplateVars {
+            greeting: current_greeting(settings, env).to_string(),
+            date: format_date(settings, env.now),
+            site: env.site.clone(),
+            user: env.user.clone(),
+            name: visitor_name(settings, env).to_string(),
+            post: env
+                .post_meta
+                .iter()
+                .map(|(key, value)| (format!("post_{}", key), value.clone()))
+                .collect(),
+        };
+        let timeout = Duration::from_millis(settings.template_timeout_ms);
+        match template::render(tpl, &vars, timeout) {
+            Ok(output) => return template_sanitizer().clean(&output).to_string(),
+            Err(e) => plugin_log!(
+                settings.log_config(),
+                WARN,
+                event = "template_failed",
+                error = e;
+                "greeting template failed to render: {}",
+                e
+            ),
+        }
+    }
+
+    builtin_greeting_html(settings, env)
+}
+
+/// Whether the markup carries its own `style` attributes
+fn styles_inline(settings: &HelloWorldSettings) -> bool {
+    settings.styling_mode == StylingMode::InlineStyle && !settings.amp_mode
+}
+
+/// Attributes of the greeting `div` besides its class
+fn greeting_attrs(settings: &HelloWorldSettings) -> String {
+    let mut attrs = if styles_inline(settings) {
+        style_attr(&css::greeting_inline_style(settings))
+    } else {
+        String::new()
+    };
+    attrs.push_str(&dir_attr(settings));
+    if let Some(live) = &settings.aria_live {
+        attrs.push_str(&format!(r#" aria-live="{}""#, escape_html(live)));
+    }
+    attrs
+}
+
+/// The "Today is <date>" line, or nothing if the date isn't shown
+fn date_line(settings: &HelloWorldSettings, now: DateTime<Utc>) -> String {
+    if !shows_date(settings, now) {
+        return String::new();
+    }
+    let date_style = if styles_inline(settings) {
+        style_attr(css::DATE_INLINE_STYLE)
+    } else {
+        String::new()
+    };
+    format!(
+        r#"<div class="hello-world-date"{}>Today is {}</div>"#,
+        date_style,
+        format_date(settings, now)
+    )
+}
+
+/// Fragments of the greeting that page builders can place on their own
+pub(crate) fn slot(settings: &HelloWorldSettings, name: &str, env: &RenderEnv) -> Option<String> {
+    match name {
+        "greeting" => Some(format!(
+            r#"<div class="hello-world-greeting"{}>{}</div>"#,
+            greeting_attrs(settings),
+            greeting_markup(settings, env)
+        )),
+        "date" => Some(date_line(settings, env.now)),
+        "widget" => Some(widget_html(settings)),
+        "footer" if settings.append_footer => Some(footer_html(settings)),
+        "footer" => Some(String::new()),
+        _ => None,
+    }
+}
+
+/// The built-in greeting markup
+fn builtin_greeting_html(settings: &HelloWorldSettings, env: &RenderEnv) -> String {
+    let now = env.now;
+    let greeting_style = greeting_attrs(settings);
+
+    if settings.date_inline {
+        let date = if shows_date(settings, now) {
+            format!(
+                r#"{}<span class="hello-world-date">{}</span>"#,
+                escape_html(&settings.greeting_date_separator),
+                format_date(settings, now)
+            )
+        } else {
+            String::new()
+        };
+        return format!(
+            r#"<div class="hello-world-greeting"{}>{}{}</div>"#,
+            greeting_style,
+            greeting_markup(settings, env),
+            date
+        );
+    }
+
+    let mut output = format!(
+        r#"<div class="hello-world-greeting"{}>{}</div>"#,
+        greeting_style,
+        greeting_markup(settings, env)
+    );
+    output.push_str(&date_line(settings, now));
+    output
+}
+
+/// The greeting for HTML email: a presentation table with every style
+/// inlined, since email clients drop `<style>` blocks and ignore classes
+pub(crate) fn greeting_email(settings: &HelloWorldSettings, env: &RenderEnv) -> String {
+    let now = env.now;
+    // Many clients don't render gradients, so lead with a solid color
+    let greeting_style = format!(
+        "background-color: {}; {}",
+        settings.gradient_start,
+        css::greeting_inline_style(settings)
+    );
+
+    let mut output = format!(
+        r#"<table role="presentation" width="100%" cellpadding="0" cellspacing="0" style="border-collapse: collapse;"><tr><td{}{}>{}</td></tr>"#,
+        style_attr(&greeting_style),
+        dir_attr(settings),
+        greeting_markup(settings, env)
+    );
+
+    if shows_date(settings, now) {
+        output.push_str(&format!(
+            r#"<tr><td{}>Today is {}</td></tr>"#,
+            style_attr(css::DATE_INLINE_STYLE),
+            format_date(settings, now)
+        ));
+    }
+
+    output.push_str("</table>");
+    output
+}
+
+/// Shown in place of a render that panicked: the greeting text alone,
+/// escaped, with nothing else that could panic again
+pub(crate) fn panic_fallback(settings: &HelloWorldSettings) -> String {
+    format!(
+        r#"<div class="hello-world-greeting">{}</div>"#,
+        escape_html(&settings.greeting_text)
+    )
+}
+
+/// Longest [`header_value`], in bytes
+const MAX_HEADER_LEN: usize = 256;
+
+/// The greeting as an HTTP header value: tags and control characters,
+/// CR and LF included, removed, whitespace collapsed and the result cut
+/// to [`MAX_HEADER_LEN`] bytes
+pub(crate) fn header_value(settings: &HelloWorldSettings, env: &RenderEnv) -> String {
+    let text = strip_tags(&current_greeting(settings, env));
+    let mut value = String::with_capacity(text.len().min(MAX_HEADER_LEN));
+    for word in text
+        .split(|c: char| c.is_whitespace() || c.is_control())
+        .filter(|word| !word.is_empty())
+    {
+        let separator = usize::from(!value.is_empty());
+        if value.len() + separator + word.len() > MAX_HEADER_LEN {
+            // A first word too long on its own is cut rather than dropped
+            if value.is_empty() {
+                let mut end = MAX_HEADER_LEN;
+                while !word.is_char_boundary(end) {
+                    end -= 1;
+                }
+                value.push_str(&word[..end]);
+            }
+            break;
+        }
+        if separator == 1 {
+            value.push(' ');
+        }
+        value.push_str(word);
+    }
+    value
+}
+
+/// Approximate width of a character in the badge's 11px font
+const BADGE_CHAR_WIDTH: usize = 7;
+
+/// Space left and right of the badge text
+const BADGE_PADDING: usize = 10;
+
+/// The greeting as a standalone SVG badge on the configured gradient, sized
+/// to the text
+pub(crate) fn svg_badge(settings: &HelloWorldSettings, env: &RenderEnv) -> String {
+    let text = strip_tags(&current_greeting(settings, env));
+    let width = text.graphemes(true).count() * BADGE_CHAR_WIDTH + 2 * BADGE_PADDING;
+    let text = escape_html(&text);
+    format!(
+        concat!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="20" "#,
+            r#"role="img" aria-label="{text}">"#,
+            r#"<linearGradient id="hello-world-badge" x2="100%">"#,
+            r#"<stop offset="0" stop-color="{start}"/><stop offset="1" stop-color="{end}"/>"#,
+            r#"</linearGradient>"#,
+            r#"<rect width="{width}" height="20" rx="3" fill="url(#hello-world-badge)"/>"#,
+            r##"<text x="{center}" y="14" fill="#fff" font-family="Verdana,sans-serif" "##,
+            r#"font-size="11" text-anchor="middle">{text}</text></svg>"#,
+        ),
+        width = width,
+        center = width / 2,
+        text = text,
+        start = escape_html(&settings.gradient_start),
+        end = escape_html(&settings.gradient_end),
+    )
+}
+
+/// ` style="..."`, ready to append to an opening tag
+fn style_attr(declarations: &str) -> String {
+    format!(r#" style="{}""#, declarations)
+}
+
+/// `html` without its `style` attributes, for AMP pages
+///
+/// Only attributes inside tags are removed; text that happens to read
+/// `style=` is left alone.
+pub(crate) fn strip_style_attrs(html: &str) -> String {
+    let lower = html.to_ascii_lowercase();
+    let mut output = String::with_capacity(html.len());
+    let (mut copied, mut searched) = (0, 0);
+    while let Some(found) = lower[searched..].find("style=") {
+        let name = searched + found;
+        let value = name + "style=".len();
+        let end = attr_value_end(html, value);
+        searched = end;
+
+        // Must be a whole attribute name, inside a tag
+        let before = &html[..name];
+        if !before.ends_with(|c: char| c.is_ascii_whitespace())
+            || before.rfind('<') <= before.rfind('>')
+        {
+            continue;
+        }
+        let start = before
+            .trim_end_matches(|c: char| c.is_ascii_whitespace())
+            .len();
+        output.push_str(&html[copied..start]);
+        copied = end;
+    }
+    output.push_str(&html[copied..]);
+    output
+}
+
+/// Where the attribute value starting at `start` of `html` ends
+fn attr_value_end(html: &str, start: usize) -> usize {
+    let rest = &html[start..];
+    match rest.chars().next() {
+        Some(quote @ ('"' | '\'')) => rest[1..]
+            .find(quote)
+            .map_or(html.len(), |close| start + close + 2),
+        _ => rest
+            .find(|c: char| c.is_ascii_whitespace() || c == '>')
+            .map_or(html.len(), |end| start + end),
+    }
+}
+
+/// The greeting and date as plain text, one per line
+pub(crate) fn greeting_plain(settings: &HelloWorldSettings, env: &RenderEnv) -> String {
+    let now = env.now;
+    let mut output = strip_tags(&current_greeting(settings, env));
+
+    if shows_date(settings, now) {
+        if settings.date_inline {
+            output.push_str(&settings.greeting_date_separator);
+            output.push_str(&format_date(settings, now));
+        } else {
+            output.push_str(&format!("\nToday is {}", format_date(settings, now)));
+        }
+    }
+
+    output
+}
+
+/// The greeting and date as a JSON object
+fn greeting_json(settings: &HelloWorldSettings, env: &RenderEnv) -> String {
+    let now = env.now;
+    let date = shows_date(settings, now).then(|| format_date(settings, now));
+    serde_json::json!({
+        "greeting": current_greeting(settings, env),
+        "date": date,
+    })
+    .to_string()
+}
+
+/// The Hello World widget
+pub(crate) fn widget_html(settings: &HelloWorldSettings) -> String {
+    widget_markup(settings, "Greeting", widget_greeting(settings))
+}
+
+/// Text of widgets that don't set their own
+fn widget_greeting(settings: &HelloWorldSettings) -> &str {
+    settings
+        .widget_greeting_text
+        .as_deref()
+        .unwrap_or(&settings.greeting_text)
+}
+
+/// One of the widgets configured in `settings.widgets`
+pub(crate) fn configured_widget_html(
+    settings: &HelloWorldSettings,
+    widget: &WidgetConfig,
+) -> String {
+    let text = widget.text.as_deref().unwrap_or(widget_greeting(settings));
+    widget_markup(settings, &widget.title, text)
+}
+
+/// Render the `[hello_widget id="..."]` shortcode; unknown ids render nothing
+pub(crate) fn widget_shortcode(settings: &HelloWorldSettings, attrs: &str) -> String {
+    let attrs = parse_shortcode_attrs(attrs);
+    let id = attrs.get("id").map(String::as_str).unwrap_or_default();
+
+    match settings.widgets.iter().find(|widget| widget.id == id) {
+        Some(widget) => configured_widget_html(settings, widget),
+        None => {
+            plugin_log!(
+                settings.log_config(),
+                DEBUG,
+                event = "unknown_widget",
+                id = id;
+                "[hello_widget] references unknown widget {:?}",
+                id
+            );
+            unknown_widget_html(settings, id)
+        }
+    }
+}
+
+/// What `[hello_widget]` renders for an id that isn't configured: nothing,
+/// or with `strict_widgets` a comment, or a visible placeholder when debug
+/// markers are on too
+fn unknown_widget_html(settings: &HelloWorldSettings, id: &str) -> String {
+    if !settings.strict_widgets {
+        String::new()
+    } else if settings.debug_markers {
+        format!(
+            r#"<div class="hello-world-widget-error">Unknown widget: {}</div>"#,
+            escape_html(id)
+        )
+    } else {
+        format!("<!-- unknown widget: {} -->", escape_html(id))
+    }
+}
+
+/// `[hello_last_updated]`: how long before `now` the settings last changed,
+/// or nothing if they never have
+pub(crate) fn last_updated_html(settings: &HelloWorldSettings, now: DateTime<Utc>) -> String {
+    match settings.last_updated {
+        Some(updated) => format!(
+            r#"<span class="hello-world-last-updated">updated {}</span>"#,
+            time_ago(updated, now)
+        ),
+        None => String::new(),
+    }
+}
+
+/// `[hello_stats]`: the render count and how long before `now` the settings
+/// last changed, for dashboards
+pub(crate) fn stats_html(
+    settings: &HelloWorldSettings,
+    render_count: u64,
+    now: DateTime<Utc>,
+) -> String {
+    let updated = match settings.last_updated {
+        Some(updated) => time_ago(updated, now),
+        None => "never".to_string(),
+    };
+    format!(
+        "<dl class=\"hello-world-stats\"><dt>Renders</dt><dd>{}</dd>\
+         <dt>Last updated</dt><dd>{}</dd></dl>",
+        render_count,
+        escape_html(&updated)
+    )
+}
+
+/// "just now", "5 minutes ago", "1 hour ago", "3 days ago", ...
+pub(crate) fn time_ago(then: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let seconds = (now - then).num_seconds().max(0);
+    let (count, unit) = match seconds {
+        0..=59 => return "just now".to_string(),
+        60..=3_599 => (seconds / 60, "minute"),
+        3_600..=86_399 => (seconds / 3_600, "hour"),
+        _ => (seconds / 86_400, "day"),
+    };
+    let plural = if count == 1 { "" } else { "s" };
+    format!("{} {}{} ago", count, unit, plural)
+}
+
+/// Time shown for each entry of [`HelloWorldSettings::clocks`]
+const CLOCK_TIME_FORMAT: &str = "%H:%M";
+
+/// The `[hello_clocks]` list: one "Label: time" item per configured clock
+///
+/// Clocks with an unknown timezone are left out.
+pub(crate) fn clocks_html(settings: &HelloWorldSettings, now: DateTime<Utc>) -> String {
+    let items: String = settings
+        .clocks
+        .iter()
+        .filter_map(|(label, timezone)| match clock::parse_timezone(timezone) {
+            Some(tz) => Some(format!(
+                "<li>{}: {}</li>",
+                escape_html(label),
+                now.with_timezone(&tz).format(CLOCK_TIME_FORMAT)
+            )),
+            None => {
+                plugin_log!(
+                    settings.log_config(),
+                    WARN,
+                    event = "unknown_timezone",
+                    label = label,
+                    timezone = timezone;
+                    "skipping clock {:?}: unknown timezone {:?}",
+                    label,
+                    timezone
+                );
+                None
+            }
+        })
+        .collect();
+
+    format!(r#"<ul class="hello-world-clocks">{}</ul>"#, items)
+}
+
+/// A widget's markup; the title is always escaped, while the text is a
+/// greeting and treated as `greeting_html` says
+fn widget_markup(settings: &HelloWorldSettings, title: &str, text: &str) -> String {
+    let text = apply_greeting_html(settings, Cow::Borrowed(text));
+    let text = match settings.widget_max_chars {
+        Some(max) => Cow::Owned(truncate_markup(&text, max).into_owned()),
+        None => text,
+    };
+
+    let mut classes = String::from("widget hello-world-widget");
+    for class in &settings.widget_extra_classes {
+        classes.push(' ');
+        classes.push_str(class);
+    }
+
+    let open = format!(r#"<div class="{}">"#, classes);
+    let heading = format!(r#"<h3 class="widget-title">{}</h3>"#, escape_html(title));
+    let paragraph = format!("<p>{}</p>", text);
+    let html = layout(
+        settings,
+        &[
+            (0, &open),
+            (1, &heading),
+            (1, r#"<div class="widget-content">"#),
+            (2, &paragraph),
+            (1, "</div>"),
+            (0, "</div>"),
+        ],
+    );
+    with_debug_markers(settings, html)
+}
+
+/// Indentation per nesting level of `pretty_html` markup
+const PRETTY_INDENT: &str = "  ";
+
+/// Join `lines` of markup, each given with its nesting depth: one per line
+/// and indented by depth with `pretty_html`, otherwise run together
+fn layout(settings: &HelloWorldSettings, lines: &[(usize, &str)]) -> String {
+    if settings.pretty_html {
+        lines
+            .iter()
+            .map(|(depth, line)| PRETTY_INDENT.repeat(*depth) + line)
+            .collect::<Vec<_>>()
+            .join("\n")
+    } else {
+        lines.iter().map(|(_, line)| *line).collect()
+    }
+}
+
+/// The date line's date, in the configured format
+///
+/// Falls back to ISO-8601 (`2024-01-05`) if the format produces nothing.
+pub(crate) fn format_date(settings: &HelloWorldSettings, now: DateTime<Utc>) -> String {
+    let local = clock::local_time(settings, now);
+    if settings.date_backend == DateBackend::Icu {
+        if let Some(date) = icu_date(settings, local.date_naive()) {
+            return date;
+        }
+    }
+    let format = if settings.date_ordinals {
+        // chrono has no ordinal specifier, so splice the day in as a literal
+        let day = local.day();
+        let ordinal = format!("{}{}", day, ordinal_suffix(day));
+        splice_day(&settings.date_format, &ordinal)
+    } else {
+        settings.date_format.clone()
+    };
+
+    // Formatting through `write!` reports bad specifiers instead of panicking
+    let mut date = String::new();
+    if write!(date, "{}", local.format(&format)).is_err() || date.trim().is_empty() {
+        plugin_log!(
+            settings.log_config(),
+            WARN,
+            event = "date_format_fallback",
+            format = settings.date_format;
+            "date format {:?} produced no output, falling back to ISO-8601",
+            settings.date_format
+        );
+        return local.format(ISO_DATE_FORMAT).to_string();
+    }
+
+    date
+}
+
+/// `date` as the CLDR long date for `date_locale`, or `None` if the locale
+/// can't be formatted
+#[cfg(feature = "icu")]
+fn icu_date(settings: &HelloWorldSettings, date: NaiveDate) -> Option<String> {
+    use icu::calendar::Date;
+    use icu::datetime::{options::length, DateFormatter};
+    use icu::locid::Locale;
+
+    let formatted = settings
+        .date_locale
+        .parse::<Locale>()
+        .map_err(|e| e.to_string())
+        .and_then(|locale| {
+            DateFormatter::try_new_with_length(&(&locale).into(), length::Date::Long)
+                .map_err(|e| e.to_string())
+        })
+        .and_then(|formatter| {
+            // chrono's months and days always fit in a u8
+            Date::try_new_iso_date(date.year(), date.month() as u8, date.day() as u8)
+                .map_err(|e| e.to_string())
+                .and_then(|date| {
+                    formatter
+                        .format_to_string(&date.to_any())
+                        .map_err(|e| e.to_string())
+                })
+        });
+
+    match formatted {
+        Ok(date) => Some(date),
+        Err(error) => {
+            plugin_log!(
+                settings.log_config(),
+                WARN,
+                event = "icu_date_fallback",
+                locale = settings.date_locale,
+                error = error;
+                "can't format dates for locale {:?} ({}), falling back to chrono",
+                settings.date_locale,
+                error
+            );
+            None
+        }
+    }
+}
+
+/// Without the `icu` feature there is only chrono
+#[cfg(not(feature = "icu"))]
+fn icu_date(_settings: &HelloWorldSettings, _date: NaiveDate) -> Option<String> {
+    None
+}
+
+/// Whether chrono understands every specifier in `format`
+pub(crate) fn is_valid_date_format(format: &str) -> bool {
+    !StrftimeItems::new(format).any(|item| matches!(item, Item::Error))
+}
+
+/// `format` with its day specifiers, `%d`, `%-d` and `%e`, replaced by
+/// `day`; an escaped `%%` is kept as it is
+fn splice_day(format: &str, day: &str) -> String {
+    let mut spliced = String::with_capacity(format.len());
+    let mut rest = format;
+    while let Some(percent) = rest.find('%') {
+        spliced.push_str(&rest[..percent]);
+        let spec = &rest[percent..];
+        let (len, is_day) = if spec.starts_with("%-d") {
+            (3, true)
+        } else if spec.starts_with("%d") || spec.starts_with("%e") {
+            (2, true)
+        } else if spec.starts_with("%%") {
+            (2, false)
+        } else {
+            // The rest of any other specifier is copied as plain text
+            (1, false)
+        };
+        spliced.push_str(if is_day { day } else { &spec[..len] });
+        rest = &spec[len..];
+    }
+    spliced.push_str(rest);
+    spliced
+}
+
+/// English ordinal suffix for a day of the month: `st`, `nd`, `rd` or `th`
+pub fn ordinal_suffix(day: u32) -> &'static str {
+    match (day % 10, day % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    }
+}
+
+/// Identifies a footer already appended to the content
+const FOOTER_MARKER: &str = r#"<div class="hello-world-footer""#;
+
+/// Append the "Powered by" footer to post content
+///
+/// Content that already carries the footer is returned unchanged, since some
+/// pipelines run `the_content` more than once. With `footer_url` set the text
+/// links there.
+///
+/// When nothing is appended the content is handed back as is, without
+/// reallocating; this runs for every post on a page.
+pub(crate) fn append_footer(settings: &HelloWorldSettings, content: String) -> String {
+    if !settings.append_footer || content.contains(FOOTER_MARKER) {
+        return content;
+    }
+
+    let separator = if settings.pretty_html { "\n" } else { "" };
+    format!("{}{}{}", content, separator, footer_html(settings))
+}
+
+/// The "Powered by" footer, linking to `footer_url` if set
+fn footer_html(settings: &HelloWorldSettings) -> String {
+    let text = match &settings.footer_url {
+        // Validation guarantees the URL needs no attribute escaping
+        Some(url) => format!(
+            r#"<a href="{}" rel="nofollow">Powered by Hello World Plugin</a>"#,
+            url
+        ),
+        None => "Powered by Hello World Plugin".to_string(),
+    };
+
+    // In AMP mode the footer is styled from the `amp-custom` block
+    let style = if settings.amp_mode {
+        String::new()
+    } else {
+        style_attr(css::FOOTER_INLINE_STYLE)
+    };
+    let open = format!(r#"<div class="hello-world-footer"{}>"#, style);
+    layout(settings, &[(0, &open), (1, &text), (0, "</div>")])
+}
+
+/// Whether `url` is an absolute `http` or `https` URL that can go in an
+/// attribute unescaped
+pub(crate) fn is_http_url(url: &str) -> bool {
+    let has_scheme = ["http://", "https://"].iter().any(|scheme| {
+        url.len() > scheme.len()
+            && url
+                .get(..scheme.len())
+                .is_some_and(|prefix| prefix.eq_ignore_ascii_case(scheme))
+    });
+    has_scheme
+        && !url.contains(|c: char| c.is_whitespace() || c.is_control() || "\"'<>`".contains(c))
+}
+
+/// Escape `text` for use in HTML content, attributes and comments
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Elements that have no closing tag
+const VOID_ELEMENTS: [&str; 6] = ["br", "hr", "img", "input", "meta", "wbr"];
+
+/// A piece of markup, as [`truncate_markup`] sees it
+enum Markup<'a> {
+    /// A tag, comment or doctype, which takes up no room
+    Tag(&'a str),
+    /// A character reference like `&amp;`, shown as one character
+    Entity(&'a str),
+    Text(&'a str),
+}
+
+/// Split `html` into tags, character references and the text between
+fn markup_pieces(html: &str) -> Vec<Markup<'_>> {
+    let mut pieces = Vec::new();
+    let mut rest = html;
+    while !rest.is_empty() {
+        let piece = if rest.starts_with('<')
+            && rest[1..].starts_with(|c: char| c.is_ascii_alphabetic() || "/!?".contains(c))
+        {
+            rest.find('>').map(|end| Markup::Tag(&rest[..=end]))
+        } else if rest.starts_with('&') {
+            rest.find(';')
+                .filter(|&end| {
+                    end > 1
+                        && rest[1..end]
+                            .bytes()
+                            .all(|b| b.is_ascii_alphanumeric() || b == b'#')
+                })
+                .map(|end| Markup::Entity(&rest[..=end]))
+        } else {
+            None
+        };
+        // A lone `<` or `&` is just text
+        let piece = piece.unwrap_or_else(|| {
+            let first = rest.chars().next().map_or(0, char::len_utf8);
+            let end = rest[first..]
+                .find(['<', '&'])
+                .map_or(rest.len(), |i| i + first);
+            Markup::Text(&rest[..end])
+        });
+        rest = match &piece {
+            Markup::Tag(s) | Markup::Entity(s) | Markup::Text(s) => &rest[s.len()..],
+        };
+        pieces.push(piece);
+    }
+    pieces
+}
+
+/// Name of the element a start tag opens, or `None` for end tags, void
+/// elements, comments and the like
+fn opened_element(tag: &str) -> Option<&str> {
+    let inner = tag.strip_prefix('<')?.strip_suffix('>')?;
+    if inner.starts_with(['/', '!', '?']) || inner.ends_with('/') {
+        return None;
+    }
+    let name = inner.split(|c: char| c.is_whitespace()).next()?;
+    (!VOID_ELEMENTS.contains(&name.to_ascii_lowercase().as_str())).then_some(name)
+}
+
+/// Cut `html` to at most `max` visible graphemes, the last being an
+/// ellipsis if anything was cut
+///
+/// Tags don't count and a character reference counts as the one character
+/// it shows, so neither is ever split. Markup is kept up to the cut, and
+/// elements left open there are closed after the ellipsis.
+pub(crate) fn truncate_markup(html: &str, max: usize) -> Cow<'_, str> {
+    let pieces = markup_pieces(html);
+    let visible: usize = pieces
+        .iter()
+        .map(|piece| match piece {
+            Markup::Tag(_) => 0,
+            Markup::Entity(_) => 1,
+            Markup::Text(text) => text.graphemes(true).count(),
+        })
+        .sum();
+    if visible <= max {
+        return Cow::Borrowed(html);
+    }
+
+    let mut budget = max.saturating_sub(1);
+    let mut truncated = String::with_capacity(html.len());
+    let mut open: Vec<&str> = Vec::new();
+    'pieces: for piece in pieces {
+        match piece {
+            Markup::Tag(tag) => {
+                if let Some(name) = tag.strip_prefix("</") {
+                    let name = name.trim_end_matches('>').trim();
+                    if let Some(i) = open
+                        .iter()
+                        .rposition(|open| open.eq_ignore_ascii_case(name))
+                    {
+                        open.truncate(i);
+                    }
+                } else if let Some(name) = opened_element(tag) {
+                    open.push(name);
+                }
+                truncated.push_str(tag);
+            }
+            _ if budget == 0 => break,
+            Markup::Entity(entity) => {
+                truncated.push_str(entity);
+                budget -= 1;
+            }
+            Markup::Text(text) => {
+                for grapheme in text.graphemes(true) {
+                    if budget == 0 {
+                        break 'pieces;
+                    }
+                    truncated.push_str(grapheme);
+                    budget -= 1;
+                }
+            }
+        }
+    }
+    if max > 0 {
+        truncated.push('…');
+    }
+    for name in open.iter().rev() {
+        let _ = write!(truncated, "</{}>", name);
+    }
+    Cow::Owned(truncated)
+}
+
+/// Whether `class` is safe to put in a `class` attribute unescaped
+pub(crate) fn is_valid_class_name(class: &str) -> bool {
+    !class.is_empty()
+        && class
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Drop anything that looks like a tag, for contexts that can't take markup
+fn strip_tags(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut in_tag = false;
+    for c in input.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            '>' => {}
+            c if !in_tag => output.push(c),
+            _ => {}
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_ordinal_suffix() {
+        assert_eq!(ordinal_suffix(1), "st");
+        assert_eq!(ordinal_suffix(2), "nd");
+        assert_eq!(ordinal_suffix(3), "rd");
+        assert_eq!(ordinal_suffix(11), "th");
+        assert_eq!(ordinal_suffix(12), "th");
+        assert_eq!(ordinal_suffix(13), "th");
+        assert_eq!(ordinal_suffix(21), "st");
+        assert_eq!(ordinal_suffix(23), "rd");
+    }
+
+    #[test]
+    fn test_format_date_ordinals() {
+        let now = Utc.with_ymd_and_hms(2024, 1, 5, 12, 0, 0).unwrap();
+        let mut settings = HelloWorldSettings::default();
+        assert_eq!(format_date(&settings, now), "January 05, 2024");
+
+        settings.date_ordinals = true;
+        assert_eq!(format_date(&settings, now), "January 5th, 2024");
+
+        settings.date_format = "%%d %-d %e%%".to_string();
+        assert_eq!(format_date(&settings, now), "%d 5th 5th%");
+    }
+
+    #[cfg(not(feature = "icu"))]
+    #[test]
+    fn test_icu_backend_without_feature_uses_chrono() {
+        let now = Utc.with_ymd_and_hms(2024, 1, 5, 12, 0, 0).unwrap();
+        let settings = HelloWorldSettings {
+            date_backend: DateBackend::Icu,
+            date_locale: "fr".to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(format_date(&settings, now), "January 05, 2024");
+    }
+
+    #[cfg(feature = "icu")]
+    #[test]
+    fn test_icu_backend_localizes_month_names() {
+        let now = Utc.with_ymd_and_hms(2024, 3, 5, 12, 0, 0).unwrap();
+        let mut settings = HelloWorldSettings {
+            date_backend: DateBackend::Icu,
+            date_locale: "de".to_string(),
+            ..Default::default()
+        };
+        assert!(format_date(&settings, now).contains("März"));
+
+        settings.date_locale = "not a locale!".to_string();
+        assert_eq!(format_date(&settings, now), "March 05, 2024");
+    }
+
+    #[test]
+    fn test_empty_date_falls_back_to_iso() {
+        let now = Utc.with_ymd_and_hms(2024, 1, 5, 12, 0, 0).unwrap();
+        let settings = HelloWorldSettings {
+            date_format: " ".to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(format_date(&settings, now), "2024-01-05");
+        assert!(
+            builtin_greeting_html(&settings, &RenderEnv::at(now)).contains("Today is 2024-01-05")
+        );
+    }
+
+    #[test]
+    fn test_debug_markers() {
+        let mut settings = HelloWorldSettings::default();
+        let env = RenderEnv::at(Utc::now());
+        assert!(!shortcode(&settings, "", &env).contains("hello-world:start"));
+        assert!(!widget_html(&settings).contains("hello-world:start"));
+
+        settings.debug_markers = true;
+        for output in [shortcode(&settings, "", &env), widget_html(&settings)] {
+            assert!(output.starts_with("<!-- hello-world:start -->"));
+            assert!(output.ends_with("<!-- hello-world:end -->"));
+        }
+    }
+
+    #[test]
+    fn test_widget_extra_classes() {
+        let settings = HelloWorldSettings {
+            widget_extra_classes: vec!["col-md-4".to_string(), "card".to_string()],
+            ..Default::default()
+        };
+        assert!(widget_html(&settings)
+            .starts_with(r#"<div class="widget hello-world-widget col-md-4 card">"#));
+    }
+
+    fn sidebar_settings() -> HelloWorldSettings {
+        HelloWorldSettings {
+            widgets: vec![WidgetConfig {
+                id: "sidebar1".to_string(),
+                title: "Welcome".to_string(),
+                text: Some("Glad you're here".to_string()),
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_widget_shortcode_known_id() {
+        let html = widget_shortcode(&sidebar_settings(), r#"id="sidebar1""#);
+        assert!(html.contains(r#"<h3 class="widget-title">Welcome</h3>"#));
+        assert!(html.contains("<p>Glad you're here</p>"));
+    }
+
+    #[test]
+    fn test_widget_shortcode_escapes_markup() {
+        let mut settings = sidebar_settings();
+        settings.widgets[0].title = "<script>alert(1)</script>".to_string();
+        settings.widgets[0].text = Some("<img src=x onerror=alert(1)>Hi".to_string());
+        let html = widget_shortcode(&settings, r#"id="sidebar1""#);
+        assert!(html.contains("&lt;script&gt;alert(1)&lt;/script&gt;</h3>"));
+        assert!(html.contains("<p>Hi</p>"));
+        assert!(!html.contains("<script") && !html.contains("onerror"));
+    }
+
+    #[test]
+    fn test_widget_shortcode_unknown_id() {
+        assert_eq!(widget_shortcode(&sidebar_settings(), r#"id="footer""#), "");
+        assert_eq!(widget_shortcode(&sidebar_settings(), ""), "");
+    }
+
+    #[test]
+    fn test_render_content_types() {
+        let settings = HelloWorldSettings::default();
+        let env = RenderEnv::at(Utc.with_ymd_and_hms(2024, 1, 5, 12, 0, 0).unwrap());
+
+        let html = render(&settings, ContentType::Html, &env);
+        assert!(html.starts_with(r#"<div class="hello-world-greeting">Hello, World!</div>"#));
+
+        let text = render(&settings, ContentType::Text, &env);
+        assert_eq!(text, "Hello, World!\nToday is January 05, 2024");
+
+        let json: serde_json::Value =
+            serde_json::from_str(&render(&settings, ContentType::Json, &env)).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({"greeting": "Hello, World!", "date": "January 05, 2024"})
+        );
+        assert_eq!(
+            shortcode(&settings, r#"format="json""#, &env),
+            json.to_string()
+        );
+    }
+
+    #[test]
+    fn test_attrs_apply_to_every_content_type() {
+        let settings = HelloWorldSettings::default();
+        let env = RenderEnv {
+            site: "Example".to_string(),
+            ..RenderEnv::at(Utc.with_ymd_and_hms(2024, 1, 5, 12, 0, 0).unwrap())
+        };
+
+        for accept in [ContentType::Html, ContentType::Text, ContentType::Json] {
+            let plain = shortcode_as(&settings, "", accept, &env);
+            let with_site = shortcode_as(&settings, r#"site="true""#, accept, &env);
+            assert_ne!(plain, with_site);
+            assert!(
+                with_site.contains("Hello, World! from Example"),
+                "{}",
+                with_site
+            );
+        }
+    }
+
+    #[test]
+    fn test_inline_styling_mode() {
+        let now = Utc::now();
+        let mut settings = HelloWorldSettings::default();
+        assert!(!builtin_greeting_html(&settings, &RenderEnv::at(now)).contains("style="));
+
+        settings.styling_mode = StylingMode::InlineStyle;
+        let html = builtin_greeting_html(&settings, &RenderEnv::at(now));
+        assert!(html.starts_with(r#"<div class="hello-world-greeting" style="padding: 20px;"#));
+        assert!(html.contains(r#"<div class="hello-world-date" style="text-align: center;"#));
+    }
+
+    #[test]
+    fn test_widget_max_chars() {
+        let settings = HelloWorldSettings {
+            greeting_text: "Welcome to our wonderful little corner of the web".to_string(),
+            widget_max_chars: Some(10),
+            ..Default::default()
+        };
+        let env = RenderEnv::at(Utc::now());
+
+        assert!(widget_html(&settings).contains("<p>Welcome t…</p>"));
+        assert!(shortcode(&settings, "", &env).contains(&settings.greeting_text));
+
+        // Allowed markup survives the cut, and doesn't count towards it
+        let settings = HelloWorldSettings {
+            greeting_text: "<b>Tom &amp; Jerry</b> say hi".to_string(),
+            widget_max_chars: Some(8),
+            ..settings
+        };
+        assert!(widget_html(&settings).contains("<p><b>Tom &amp; J…</b></p>"));
+    }
+
+    #[test]
+    fn test_truncate_markup() {
+        assert_eq!(truncate_markup("short", 10), "short");
+        assert_eq!(truncate_markup("🇯🇵🇫🇷🇩🇪", 2), "🇯🇵…");
+        assert_eq!(truncate_markup("abc", 0), "");
+
+        // A reference at the cut is kept or dropped whole
+        assert_eq!(truncate_markup("Tom &amp; Jerry", 6), "Tom &amp;…");
+        assert_eq!(truncate_markup("Tom &amp; Jerry", 5), "Tom …");
+        assert_eq!(
+            truncate_markup("<em>Hi<br>there</em> you", 5),
+            "<em>Hi<br>th…</em>"
+        );
+        assert_eq!(truncate_markup("1 < 2 & 3 > 2", 6), "1 < 2…");
+        assert_eq!(truncate_markup("étés été", 3), "ét…");
+    }
+
+    #[test]
+    fn test_date_uses_timezone() {
+        let now = Utc.with_ymd_and_hms(2024, 1, 5, 23, 30, 0).unwrap();
+        let settings = HelloWorldSettings {
+            timezone: "Asia/Tokyo".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(format_date(&settings, now), "January 06, 2024");
+    }
+
+    #[test]
+    fn test_append_footer_is_idempotent() {
+        let settings = HelloWorldSettings::default();
+        let once = append_footer(&settings, "<p>Post</p>".to_string());
+        let twice = append_footer(&settings, once.clone());
+        assert_eq!(twice, once);
+        assert_eq!(twice.matches(FOOTER_MARKER).count(), 1);
+    }
+
+    #[test]
+    fn test_greeting_email_is_inline_styled() {
+        let now = Utc.with_ymd_and_hms(2024, 1, 5, 12, 0, 0).unwrap();
+        let html = greeting_email(&HelloWorldSettings::default(), &RenderEnv::at(now));
+        assert!(html.contains(r#"<td style="background-color: #667eea; padding: 20px;"#));
+        assert!(html.contains("Today is January 05, 2024"));
+        assert!(!html.contains("class="));
+    }
+
+    #[test]
+    fn test_hide_date_weekends() {
+        let settings = HelloWorldSettings {
+            hide_date_weekends: true,
+            ..Default::default()
+        };
+        let friday = Utc.with_ymd_and_hms(2024, 1, 5, 12, 0, 0).unwrap();
+        let saturday = Utc.with_ymd_and_hms(2024, 1, 6, 12, 0, 0).unwrap();
+        assert!(greeting_plain(&settings, &RenderEnv::at(friday)).contains("Today is"));
+        assert!(!greeting_plain(&settings, &RenderEnv::at(saturday)).contains("Today is"));
+    }
+
+    #[test]
+    fn test_disabled_footer_does_not_reallocate() {
+        let settings = HelloWorldSettings {
+            append_footer: false,
+            ..Default::default()
+        };
+        let content = String::with_capacity(1024) + "<p>Post</p>";
+        let (ptr, capacity) = (content.as_ptr(), content.capacity());
+
+        let output = append_footer(&settings, content);
+        assert_eq!(output, "<p>Post</p>");
+        assert_eq!(output.as_ptr(), ptr);
+        assert_eq!(output.capacity(), capacity);
+    }
+
+    #[test]
+    fn test_footer_url() {
+        let settings = HelloWorldSettings::default();
+        assert!(!append_footer(&settings, String::new()).contains("<a "));
+
+        let settings = HelloWorldSettings {
+            footer_url: Some("https://example.com/hello".to_string()),
+            ..Default::default()
+        };
+        assert!(append_footer(&settings, String::new()).contains(
+            r#"<a href="https://example.com/hello" rel="nofollow">Powered by Hello World Plugin</a>"#
+        ));
+    }
+
+    #[test]
+    fn test_is_http_url() {
+        assert!(is_http_url("https://example.com/greeting"));
+        assert!(is_http_url("HTTP://example.com"));
+        assert!(!is_http_url("https://"));
+        assert!(!is_http_url("ftp://example.com"));
+        assert!(!is_http_url("javascript:alert(1)"));
+        assert!(!is_http_url(r#"https://example.com/"onmouseover="x"#));
+    }
+
+    #[test]
+    fn test_clocks_html() {
+        let now = Utc.with_ymd_and_hms(2024, 1, 5, 12, 0, 0).unwrap();
+        let settings = HelloWorldSettings {
+            clocks: vec![
+                ("Berlin".to_string(), "Europe/Berlin".to_string()),
+                ("Tokyo".to_string(), "Asia/Tokyo".to_string()),
+                ("Atlantis".to_string(), "Ocean/Atlantis".to_string()),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            clocks_html(&settings, now),
+            r#"<ul class="hello-world-clocks"><li>Berlin: 13:00</li><li>Tokyo: 21:00</li></ul>"#
+        );
+
+        let settings = HelloWorldSettings {
+            clocks: vec![("<b>UTC</b>".to_string(), "UTC".to_string())],
+            ..Default::default()
+        };
+        assert!(clocks_html(&settings, now).contains("<li>&lt;b&gt;UTC&lt;/b&gt;: 12:00</li>"));
+    }
+
+    #[test]
+    fn test_strict_widgets_marks_unknown_ids() {
+        let strict = HelloWorldSettings {
+            strict_widgets: true,
+            ..sidebar_settings()
+        };
+        assert_eq!(
+            widget_shortcode(&strict, r#"id="x-->""#),
+            "<!-- unknown widget: x--&gt; -->"
+        );
+
+        let debug = HelloWorldSettings {
+            debug_markers: true,
+            ..strict
+        };
+        assert!(widget_shortcode(&debug, r#"id="x""#).contains("Unknown widget: x</div>"));
+    }
+
+    #[test]
+    fn test_inline_date_uses_separator() {
+        let env = RenderEnv::at(Utc.with_ymd_and_hms(2024, 1, 5, 12, 0, 0).unwrap());
+        let settings = HelloWorldSettings {
+            date_inline: true,
+            ..Default::default()
+        };
+        assert!(builtin_greeting_html(&settings, &env).contains(
+            r#">Hello, World! — <span class="hello-world-date">January 05, 2024</span></div>"#
+        ));
+
+        let settings = HelloWorldSettings {
+            greeting_date_separator: " <|> ".to_string(),
+            ..settings
+        };
+        assert_eq!(
+            greeting_plain(&settings, &env),
+            "Hello, World! <|> January 05, 2024"
+        );
+        assert!(builtin_greeting_html(&settings, &env)
+            .contains(r#"Hello, World! &lt;|&gt; <span class="hello-world-date">"#));
+    }
+
+    #[test]
+    fn test_last_updated() {
+        let now = Utc.with_ymd_and_hms(2024, 1, 5, 12, 0, 0).unwrap();
+        let settings = HelloWorldSettings {
+            last_updated: Some(now - chrono::Duration::minutes(5)),
+            ..Default::default()
+        };
+        assert_eq!(
+            last_updated_html(&settings, now),
+            r#"<span class="hello-world-last-updated">updated 5 minutes ago</span>"#
+        );
+        assert_eq!(
+            time_ago(now - chrono::Duration::hours(1), now),
+            "1 hour ago"
+        );
+        assert_eq!(last_updated_html(&HelloWorldSettings::default(), now), "");
+    }
+
+    #[test]
+    fn test_strip_style_attrs() {
+        let html =
+            r#"<div class="a" style="color: red"><p STYLE='x'>style="kept"</p><br style=y></div>"#;
+        assert_eq!(
+            strip_style_attrs(html),
+            r#"<div class="a"><p>style="kept"</p><br></div>"#
+        );
+    }
+
+    #[test]
+    fn test_pretty_html_indents_widget() {
+        let mut settings = HelloWorldSettings {
+            greeting_text: "Hi".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            widget_html(&settings),
+            "<div class=\"widget hello-world-widget\"><h3 class=\"widget-title\">Greeting</h3>\
+             <div class=\"widget-content\"><p>Hi</p></div></div>"
+        );
+
+        settings.pretty_html = true;
+        assert_eq!(
+            widget_html(&settings),
+            [
+                r#"<div class="widget hello-world-widget">"#,
+                r#"  <h3 class="widget-title">Greeting</h3>"#,
+                r#"  <div class="widget-content">"#,
+                r#"    <p>Hi</p>"#,
+                r#"  </div>"#,
+                r#"</div>"#,
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_stats() {
+        let now = Utc.with_ymd_and_hms(2024, 1, 5, 12, 0, 0).unwrap();
+        let settings = HelloWorldSettings {
+            last_updated: Some(now - chrono::Duration::days(3)),
+            ..Default::default()
+        };
+        assert_eq!(
+            stats_html(&settings, 42, now),
+            "<dl class=\"hello-world-stats\"><dt>Renders</dt><dd>42</dd>\
+             <dt>Last updated</dt><dd>3 days ago</dd></dl>"
+        );
+        assert!(stats_html(&HelloWorldSettings::default(), 0, now).contains("<dd>never</dd>"));
+    }
+
+    #[test]
+    fn test_slow_template_falls_back_to_builtin() {
+        let env = RenderEnv::at(Utc.with_ymd_and_hms(2024, 1, 5, 12, 0, 0).unwrap());
+        let settings = HelloWorldSettings {
+            template: Some("{{test_block \"slow\"}}{{greeting}}".to_string()),
+            template_timeout_ms: 50,
+            ..Default::default()
+        };
+        assert_eq!(
+            greeting_html(&settings, &env),
+            builtin_greeting_html(&settings, &env)
+        );
+        // Let the stuck render finish
+        template::test_barrier().wait();
+
+        // A template that timed out isn't tried again, so this can't block
+        assert_eq!(
+            greeting_html(&settings, &env),
+            builtin_greeting_html(&settings, &env)
+        );
+    }
+
+    #[test]
+    fn test_skip_for_bots_renders_bare_greeting() {
+        let settings = HelloWorldSettings {
+            skip_for_bots: true,
+            styling_mode: StylingMode::InlineStyle,
+            ..Default::default()
+        };
+        let render_for = |user_agent: &str| {
+            let mut ctx = AppContext::new();
+            ctx.insert(UserAgent(user_agent.to_string()));
+            let env = RenderEnv::from_context(&ctx, Utc::now());
+            shortcode(&settings, "", &env)
+        };
+
+        assert_eq!(
+            render_for("Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)"),
+            r#"<div class="hello-world-greeting">Hello, World!</div>"#
+        );
+
+        let full =
+            render_for("Mozilla/5.0 (X11; Linux x86_64; rv:131.0) Gecko/20100101 Firefox/131.0");
+        assert!(full.contains("style="));
+        assert!(full.contains("Today is"));
+    }
+
+    #[test]
+    fn test_template_reads_post_meta() {
+        let mut ctx = AppContext::new();
+        ctx.insert(PostMeta(HashMap::from([(
+            "title".to_string(),
+            "Rust Weekly".to_string(),
+        )])));
+        let env = RenderEnv::from_context(&ctx, Utc::now());
+        let settings = HelloWorldSettings {
+            template: Some("Hello, readers of {{post_title}}{{post_author}}".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            greeting_html(&settings, &env),
+            "Hello, readers of Rust Weekly"
+        );
+
+        // Present but empty is still rendered; unavailable falls back
+        let env = RenderEnv::at(Utc::now());
+        assert_eq!(greeting_html(&settings, &env), "Hello, readers of ");
+        let env = RenderEnv::detached(env.now);
+        assert_eq!(
+            greeting_html(&settings, &env),
+            builtin_greeting_html(&settings, &env)
+        );
+    }
+
+    #[test]
+    fn test_direction_sets_dir() {
+        let env = RenderEnv::at(Utc::now());
+        let mut settings = HelloWorldSettings {
+            greeting_text: "مرحبا بالعالم".to_string(),
+            date_mode: DateMode::Hidden,
+            ..Default::default()
+        };
+        assert!(!greeting_html(&settings, &env).contains("dir="));
+
+        for (direction, dir) in [
+            (Direction::Auto, "auto"),
+            (Direction::Ltr, "ltr"),
+            (Direction::Rtl, "rtl"),
+        ] {
+            settings.direction = Some(direction);
+            assert_eq!(
+                greeting_html(&settings, &env),
+                format!(
+                    r#"<div class="hello-world-greeting" dir="{}">مرحبا بالعالم</div>"#,
+                    dir
+                )
+            );
+            assert!(greeting_email(&settings, &env).contains(&format!(r#" dir="{}">"#, dir)));
+        }
+    }
+
+    #[test]
+    fn test_widget_greeting_text() {
+        let env = RenderEnv::at(Utc::now());
+        let mut settings = HelloWorldSettings::default();
+        let shortcode_before = shortcode(&settings, "", &env);
+
+        settings.widget_greeting_text = Some("Hello from the sidebar!".to_string());
+        assert!(widget_html(&settings).contains("<p>Hello from the sidebar!</p>"));
+        assert_eq!(shortcode(&settings, "", &env), shortcode_before);
+        assert!(!shortcode_before.contains("sidebar"));
+    }
+
+    #[test]
+    fn test_slots() {
+        let now = Utc.with_ymd_and_hms(2024, 1, 5, 12, 0, 0).unwrap();
+        let env = RenderEnv::at(now);
+        let mut settings = HelloWorldSettings::default();
+        let render_slot = |settings: &HelloWorldSettings, name| slot(settings, name, &env);
+
+        assert_eq!(
+            render_slot(&settings, "greeting").unwrap(),
+            r#"<div class="hello-world-greeting">Hello, World!</div>"#
+        );
+        assert_eq!(
+            render_slot(&settings, "date").unwrap(),
+            r#"<div class="hello-world-date">Today is January 05, 2024</div>"#
+        );
+        assert_eq!(
+            render_slot(&settings, "widget").unwrap(),
+            widget_html(&settings)
+        );
+        assert!(render_slot(&settings, "footer")
+            .unwrap()
+            .starts_with(r#"<div class="hello-world-footer""#));
+        assert_eq!(render_slot(&settings, "sidebar"), None);
+
+        settings.date_mode = DateMode::Hidden;
+        settings.append_footer = false;
+        assert_eq!(render_slot(&settings, "date").unwrap(), "");
+        assert_eq!(render_slot(&settings, "footer").unwrap(), "");
+    }
+
+    #[test]
+    fn test_anonymous_visitors_get_default_name() {
+        let mut settings = HelloWorldSettings {
+            greeting_text: "Hello, {name}!".to_string(),
+            ..Default::default()
+        };
+        let mut env = RenderEnv::at(Utc::now());
+        assert_eq!(current_greeting(&settings, &env), "Hello, World!");
+
+        settings.default_name = "friend".to_string();
+        settings.template = Some("<p>Hi {{name}}</p>".to_string());
+        assert_eq!(greeting_html(&settings, &env), "<p>Hi friend</p>");
+
+        env.user = "<Ada>".to_string();
+        settings.template = None;
+        settings.greeting_html = GreetingHtmlMode::Raw;
+        assert!(greeting_html(&settings, &env).contains("Hello, &lt;Ada&gt;!"));
+    }
+
+    #[test]
+    fn test_first_visit_greeting() {
+        let settings = HelloWorldSettings {
+            first_visit_greeting: Some("Welcome!".to_string()),
+            ..Default::default()
+        };
+        let greeting_for = |visitor: Option<ReturningVisitor>| {
+            let mut ctx = AppContext::new();
+            if let Some(visitor) = visitor {
+                ctx.insert(visitor);
+            }
+            current_greeting(&settings, &RenderEnv::from_context(&ctx, Utc::now())).into_owned()
+        };
+
+        assert_eq!(greeting_for(Some(ReturningVisitor(false))), "Welcome!");
+        assert_eq!(greeting_for(Some(ReturningVisitor(true))), "Hello, World!");
+        assert_eq!(greeting_for(None), "Hello, World!");
+    }
+
+    #[test]
+    fn test_aria_live() {
+        let env = RenderEnv::at(Utc::now());
+        let html = builtin_greeting_html(&HelloWorldSettings::default(), &env);
+        assert!(!html.contains("aria-live"));
+
+        let settings = HelloWorldSettings {
+            aria_live: Some("polite".to_string()),
+            ..Default::default()
+        };
+        let html = builtin_greeting_html(&settings, &env);
+        assert!(html.starts_with(r#"<div class="hello-world-greeting" aria-live="polite">"#));
+    }
+
+    #[test]
+    fn test_svg_badge() {
+        let env = RenderEnv::at(Utc::now());
+        let settings = HelloWorldSettings {
+            greeting_text: "Tom & Jerry say <hi>".to_string(),
+            ..Default::default()
+        };
+        let svg = svg_badge(&settings, &env);
+        assert!(svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg""#));
+        assert!(svg.ends_with("</svg>"));
+        assert!(svg.contains(">Tom &amp; Jerry say </text>"));
+        assert!(svg.contains(r##"stop-color="#667eea""##));
+        assert_eq!(svg.matches('<').count(), svg.matches('>').count());
+
+        let short = svg_badge(&HelloWorldSettings::default(), &env);
+        assert!(short.contains(r#"width="111""#), "{}", short);
+    }
+
+    #[test]
+    fn test_header_value_is_single_line() {
+        let env = RenderEnv::at(Utc::now());
+        let settings = HelloWorldSettings {
+            greeting_text: "Hi\r\nSet-Cookie: x=1\u{0}\tthere".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(header_value(&settings, &env), "Hi Set-Cookie: x=1 there");
+
+        let settings = HelloWorldSettings {
+            greeting_text: "word ".repeat(100),
+            ..Default::default()
+        };
+        assert!(header_value(&settings, &env).len() <= MAX_HEADER_LEN);
+
+        let settings = HelloWorldSettings {
+            greeting_text: "é".repeat(MAX_HEADER_LEN),
+            ..Default::default()
+        };
+        let value = header_value(&settings, &env);
+        assert!(!value.is_empty() && value.len() <= MAX_HEADER_LEN);
+    }
+
+    #[test]
+    fn test_greeting_html_modes() {
+        let env = RenderEnv::at(Utc::now());
+        let settings = |greeting_html| HelloWorldSettings {
+            greeting_text: "<em>Hi</em><script>alert(1)</script><br>there".to_string(),
+            greeting_html,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            greeting_markup(&settings(GreetingHtmlMode::Escape), &env),
+            "&lt;em&gt;Hi&lt;/em&gt;&lt;script&gt;alert(1)&lt;/script&gt;&lt;br&gt;there"
+        );
+        assert_eq!(
+            greeting_markup(&settings(GreetingHtmlMode::SafeSubset), &env),
+            "<em>Hi</em><br>there"
+        );
+        assert_eq!(
+            greeting_markup(&settings(GreetingHtmlMode::Raw), &env),
+            "<em>Hi</em><script>alert(1)</script><br>there"
+        );
+
+        // The widget shows the same greeting, so is treated the same way
+        assert!(widget_html(&settings(GreetingHtmlMode::Escape)).contains(
+            "<p>&lt;em&gt;Hi&lt;/em&gt;&lt;script&gt;alert(1)&lt;/script&gt;&lt;br&gt;there</p>"
+        ));
+        assert!(widget_html(&settings(GreetingHtmlMode::SafeSubset))
+            .contains("<p><em>Hi</em><br>there</p>"));
+    }
+
+    #[test]
+    fn test_safe_subset_drops_attributes() {
+        let env = RenderEnv::at(Utc::now());
+        let settings = HelloWorldSettings {
+            greeting_text: r#"<strong onclick="steal()">Hi</strong> <a href="x">you</a>"#
+                .to_string(),
+            ..Default::default()
+        };
+        assert_eq!(greeting_markup(&settings, &env), "<strong>Hi</strong> you");
+    }
+
+    #[test]
+    fn test_template_sanitizer() {
+        let html = concat!(
+            r#"<p class="a" onclick="x()">Hi</p><SCRIPT>alert("x")</script>"#,
+            r#"<img src=x onerror=alert(1)><a href="javascript:x()" style="color: red">there</a>"#,
+        );
+        assert_eq!(
+            template_sanitizer().clean(html).to_string(),
+            r#"<p class="a">Hi</p><a rel="noopener noreferrer">there</a>"#
+        );
+    }
+}
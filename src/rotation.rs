@@ -0,0 +1,105 @@
+//! Rotating through several greetings
+//!
+//! Renders are numbered by a per-instance step counter that every render
+//! advances; [`Rotation`] turns the step (or the date and site) into a pick
+//! from [`HelloWorldSettings::greetings`].
+
+use crate::clock;
+use crate::render::RenderEnv;
+use crate::HelloWorldSettings;
+use chrono::Datelike;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+
+/// How the greeting is chosen from `greetings`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rotation {
+    /// Always `greeting_text`; `greetings` is ignored
+    #[default]
+    Fixed,
+    /// Each render shows the next greeting, wrapping around
+    Sequential,
+    /// Each render shows a randomly picked greeting
+    Random,
+    /// One greeting per day, in order, starting at a point that depends
+    /// on the site
+    Daily,
+}
+
+/// The greeting `settings.rotation` picks for the render `env` describes, or
+/// `None` if the rotation is off or there is nothing to rotate through
+pub(crate) fn pick<'a>(settings: &'a HelloWorldSettings, env: &RenderEnv) -> Option<&'a str> {
+    let len = settings.greetings.len() as u64;
+    if len == 0 {
+        return None;
+    }
+
+    let index = match settings.rotation {
+        Rotation::Fixed => return None,
+        Rotation::Sequential => env.rotation_step % len,
+        // A fresh `RandomState` is randomly keyed, so equal steps still
+        // give different picks
+        Rotation::Random => RandomState::new().hash_one(env.rotation_step) % len,
+        Rotation::Daily => {
+            let day = clock::local_time(settings, env.now).num_days_from_ce();
+            let offset = crate::fnv1a(env.site_id.as_bytes());
+            (u64::from(day.unsigned_abs()) % len + offset % len) % len
+        }
+    };
+    settings.greetings.get(index as usize).map(String::as_str)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, TimeZone, Utc};
+
+    fn greetings(rotation: Rotation) -> HelloWorldSettings {
+        HelloWorldSettings {
+            greetings: vec!["Hi".to_string(), "Hey".to_string(), "Howdy".to_string()],
+            rotation,
+            ..Default::default()
+        }
+    }
+
+    fn env(now: DateTime<Utc>, step: u64, site_id: &str) -> RenderEnv {
+        RenderEnv {
+            rotation_step: step,
+            site_id: site_id.to_string(),
+            ..RenderEnv::at(now)
+        }
+    }
+
+    #[test]
+    fn test_pick() {
+        let now = Utc.with_ymd_and_hms(2024, 1, 5, 12, 0, 0).unwrap();
+        assert_eq!(pick(&greetings(Rotation::Fixed), &env(now, 1, "")), None);
+        assert_eq!(
+            pick(&greetings(Rotation::Sequential), &env(now, 4, "")),
+            Some("Hey")
+        );
+
+        let daily = greetings(Rotation::Daily);
+        let tomorrow = now + chrono::Duration::days(1);
+        assert_ne!(
+            pick(&daily, &env(now, 0, "")),
+            pick(&daily, &env(tomorrow, 0, ""))
+        );
+        assert_eq!(
+            pick(&daily, &env(now, 0, "")),
+            pick(&daily, &env(now, 7, ""))
+        );
+    }
+
+    #[test]
+    fn test_daily_rotation_depends_on_site() {
+        let now = Utc.with_ymd_and_hms(2024, 1, 5, 12, 0, 0).unwrap();
+        let daily = greetings(Rotation::Daily);
+
+        let site_a = pick(&daily, &env(now, 0, "site-a"));
+        assert_ne!(site_a, pick(&daily, &env(now, 0, "blog.example.com")));
+        assert_eq!(site_a, pick(&daily, &env(now, 5, "site-a")));
+    }
+}
@@ -0,0 +1,94 @@
+//! Settings persistence
+//!
+//! The host provides a [`SettingsStore`] through the [`AppContext`]; the plugin
+//! serializes its settings to JSON and stores them under its plugin id. Hosts
+//! without one get a [`MemoryStore`], so settings survive deactivation but not
+//! a restart.
+//!
+//! [`AppContext`]: rustpress_core::context::AppContext
+
+use async_trait::async_trait;
+use parking_lot::RwLock;
+use rustpress_core::context::AppContext;
+use rustpress_core::error::Result;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Delay before the first retry; doubled after every failed attempt
+pub const INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Key/value storage for serialized plugin settings
+#[async_trait]
+pub trait SettingsStore: Send + Sync {
+    /// Load the raw settings stored under `key`, if any
+    async fn load(&self, key: &str) -> Result<Option<String>>;
+
+    /// Store the raw settings under `key`
+    async fn save(&self, key: &str, value: &str) -> Result<()>;
+
+    /// Load several keys at once, omitting the ones with nothing stored
+    ///
+    /// The default loads them one by one; stores that can fetch many keys
+    /// in a single round-trip should override it.
+    async fn load_many(&self, keys: &[&str]) -> Result<HashMap<String, String>> {
+        let mut values = HashMap::new();
+        for key in keys {
+            if let Some(value) = self.load(key).await? {
+                values.insert(key.to_string(), value);
+            }
+        }
+        Ok(values)
+    }
+}
+
+/// Process-local store used when the host doesn't provide one
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    values: RwLock<HashMap<String, String>>,
+}
+
+#[async_trait]
+impl SettingsStore for MemoryStore {
+    async fn load(&self, key: &str) -> Result<Option<String>> {
+        Ok(self.values.read().get(key).cloned())
+    }
+
+    async fn save(&self, key: &str, value: &str) -> Result<()> {
+        self.values
+            .write()
+            .insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+}
+
+/// The store registered in `ctx`, if any
+pub(crate) fn from_context(ctx: &AppContext) -> Option<Arc<dyn SettingsStore>> {
+    ctx.get::<Arc<dyn SettingsStore>>()
+        .map(|store| Arc::clone(&*store))
+}
+
+/// Run `op` up to `max_attempts` times with exponential backoff between
+/// attempts, returning the last error if every attempt fails
+pub(crate) async fn with_retry<T, F, Fut>(max_attempts: u32, mut op: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let max_attempts = max_attempts.max(1);
+    let mut backoff = INITIAL_BACKOFF;
+    let mut attempt = 1;
+
+    loop {
+        match op().await {
+            Ok(value) => return Ok(value),
+            Err(_) if attempt < max_attempts => {
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+                attempt += 1;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
@@ -0,0 +1,266 @@
+//! User-supplied greeting templates
+//!
+//! Templates use Handlebars syntax and are rendered with [`TemplateVars`].
+//! Variables are HTML-escaped by Handlebars; the rendered output is
+//! additionally passed through the plugin's sanitizer by the caller.
+//!
+//! Parsed templates are cached process-wide, keyed by their source, so a
+//! template is only parsed on its first render. Each is parsed into its own
+//! registry and renders only share an `Arc` of it, so no lock is held while
+//! a template renders.
+//!
+//! Rendering runs on a long-lived worker thread so a pathological template
+//! can't hang the request. Past the timeout the caller gets an error, the
+//! stuck worker is replaced by a fresh one for later renders, and the
+//! template is never tried again.
+
+use handlebars::Handlebars;
+use parking_lot::{Mutex, RwLock};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, OnceLock};
+use std::thread;
+use std::time::Duration;
+
+/// Cached templates beyond which the cache is emptied and refilled
+const MAX_CACHED_TEMPLATES: usize = 32;
+
+/// Variables available to a greeting template
+#[derive(Debug, Clone, Default, Serialize)]
+pub(crate) struct TemplateVars {
+    pub greeting: String,
+    pub date: String,
+    pub site: String,
+    pub user: String,
+    /// `user`, or `default_name` for anonymous visitors
+    pub name: String,
+    /// `post_<key>` for each post metadata entry
+    #[serde(flatten)]
+    pub post: HashMap<String, String>,
+}
+
+/// Check that `template` parses, returning the syntax error if not
+pub(crate) fn check(template: &str) -> Result<(), String> {
+    Handlebars::new()
+        .register_template_string("greeting", template)
+        .map_err(|e| e.to_string())
+}
+
+/// Render `template` with `vars`, giving up after `timeout`
+///
+/// A zero `timeout` renders on the calling thread without a limit, as does
+/// every render on wasm32, which has no threads. A template that once ran
+/// past its timeout fails straight away from then on.
+pub(crate) fn render(
+    template: &str,
+    vars: &TemplateVars,
+    timeout: Duration,
+) -> Result<String, String> {
+    if timed_out().read().contains(template) {
+        return Err("timed out on an earlier render, not retrying".to_string());
+    }
+    if timeout.is_zero() || cfg!(target_arch = "wasm32") {
+        return render_cached(template, vars);
+    }
+
+    let source = template.to_string();
+    let vars = vars.clone();
+    match with_deadline(timeout, move || render_cached(&source, &vars)) {
+        Ok(result) => result,
+        Err(overrun) => {
+            // A render that never started says nothing about the template
+            if overrun == Overrun::Ran {
+                timed_out().write().insert(template.to_string());
+            }
+            Err(format!("timed out after {}ms", timeout.as_millis()))
+        }
+    }
+}
+
+/// How a job run with [`with_deadline`] missed its deadline
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Overrun {
+    /// The job started and was still running
+    Ran,
+    /// The job was still queued behind one that got the worker stuck
+    Queued,
+}
+
+/// Run `job` on the worker thread, failing if it doesn't finish within
+/// `timeout`
+fn with_deadline<F>(timeout: Duration, job: F) -> Result<Result<String, String>, Overrun>
+where
+    F: FnOnce() -> Result<String, String> + Send + 'static,
+{
+    let (sender, receiver) = mpsc::channel();
+    let started = Arc::new(AtomicBool::new(false));
+    let job_started = Arc::clone(&started);
+    let generation = match submit(Box::new(move || {
+        job_started.store(true, Ordering::SeqCst);
+        // The caller may have stopped waiting
+        let _ = sender.send(job());
+    })) {
+        Ok(generation) => generation,
+        Err(e) => return Ok(Err(e)),
+    };
+
+    match receiver.recv_timeout(timeout) {
+        Ok(result) => Ok(result),
+        Err(RecvTimeoutError::Timeout) => {
+            // The worker is stuck, if not on this job then on one before it
+            retire(generation);
+            if started.load(Ordering::SeqCst) {
+                Err(Overrun::Ran)
+            } else {
+                Err(Overrun::Queued)
+            }
+        }
+        Err(RecvTimeoutError::Disconnected) => Ok(Err("template renderer panicked".to_string())),
+    }
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// The thread templates are rendered on, with the queue feeding it
+struct Worker {
+    /// Tells this worker apart from the ones that replace it
+    generation: u64,
+    jobs: mpsc::Sender<Job>,
+}
+
+static WORKER: Mutex<Option<Worker>> = parking_lot::const_mutex(None);
+
+/// Queue `job` on the current worker, starting one if there is none,
+/// returning the generation of the worker it was queued on
+fn submit(job: Job) -> Result<u64, String> {
+    static GENERATIONS: AtomicU64 = AtomicU64::new(0);
+
+    let mut slot = WORKER.lock();
+    let worker = match slot.take() {
+        Some(worker) => worker,
+        None => spawn_worker(GENERATIONS.fetch_add(1, Ordering::Relaxed))?,
+    };
+    let queued = worker.jobs.send(job);
+    let generation = worker.generation;
+    *slot = Some(worker);
+    queued
+        .map(|()| generation)
+        .map_err(|_| "template worker has stopped".to_string())
+}
+
+fn spawn_worker(generation: u64) -> Result<Worker, String> {
+    let (jobs, queue) = mpsc::channel::<Job>();
+    thread::Builder::new()
+        .name("hello-world-template".to_string())
+        .spawn(move || {
+            // Runs until the worker is retired and its queue is drained. A
+            // panicking job drops its result sender, which its caller sees.
+            for job in queue {
+                let _ = panic::catch_unwind(AssertUnwindSafe(job));
+            }
+        })
+        .map_err(|e| e.to_string())?;
+    Ok(Worker { generation, jobs })
+}
+
+/// Stop queueing on worker `generation`, if it's still the current one, so
+/// the next job starts a fresh worker
+fn retire(generation: u64) {
+    let mut slot = WORKER.lock();
+    if slot
+        .as_ref()
+        .is_some_and(|worker| worker.generation == generation)
+    {
+        *slot = None;
+    }
+}
+
+fn render_cached(template: &str, vars: &TemplateVars) -> Result<String, String> {
+    let cached = cache().read().get(template).cloned();
+    let registry = match cached {
+        Some(registry) => registry,
+        None => {
+            let registry = Arc::new(compile(template)?);
+            let mut cache = cache().write();
+            if cache.len() >= MAX_CACHED_TEMPLATES {
+                cache.clear();
+            }
+            cache.insert(template.to_string(), Arc::clone(&registry));
+            registry
+        }
+    };
+    registry
+        .render(TEMPLATE_NAME, vars)
+        .map_err(|e| e.to_string())
+}
+
+/// Name of the one template in each cached registry
+const TEMPLATE_NAME: &str = "greeting";
+
+/// A registry holding just `template`
+fn compile(template: &str) -> Result<Handlebars<'static>, String> {
+    let mut registry = Handlebars::new();
+    registry
+        .register_template_string(TEMPLATE_NAME, template)
+        .map_err(|e| e.to_string())?;
+    #[cfg(test)]
+    registry.register_helper("test_block", Box::new(test_block));
+    Ok(registry)
+}
+
+/// Whether `template` has been parsed and cached
+pub(crate) fn is_cached(template: &str) -> bool {
+    cache().read().contains_key(template)
+}
+
+fn cache() -> &'static RwLock<HashMap<String, Arc<Handlebars<'static>>>> {
+    static CACHE: OnceLock<RwLock<HashMap<String, Arc<Handlebars<'static>>>>> = OnceLock::new();
+    CACHE.get_or_init(RwLock::default)
+}
+
+/// Sources of the templates that ran past their timeout
+fn timed_out() -> &'static RwLock<HashSet<String>> {
+    static TIMED_OUT: OnceLock<RwLock<HashSet<String>>> = OnceLock::new();
+    TIMED_OUT.get_or_init(RwLock::default)
+}
+
+/// Barrier a template calling `{{test_block "..."}}` waits on, so tests can
+/// hold a render up for as long as they need
+#[cfg(test)]
+pub(crate) fn test_barrier() -> &'static std::sync::Barrier {
+    static BARRIER: OnceLock<std::sync::Barrier> = OnceLock::new();
+    BARRIER.get_or_init(|| std::sync::Barrier::new(2))
+}
+
+#[cfg(test)]
+handlebars::handlebars_helper!(test_block: |_name: str| {
+    test_barrier().wait();
+    ""
+});
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slow_render_times_out() {
+        let (release, blocked) = mpsc::channel::<()>();
+        let result = with_deadline(Duration::from_millis(10), move || {
+            let _ = blocked.recv();
+            Ok("too late".to_string())
+        });
+        assert_eq!(result, Err(Overrun::Ran));
+        release.send(()).unwrap();
+
+        // A fresh worker takes over from the stuck one
+        let vars = TemplateVars {
+            greeting: "Hi".to_string(),
+            ..Default::default()
+        };
+        let result = render("<b>{{greeting}}</b>", &vars, Duration::from_secs(5));
+        assert_eq!(result.as_deref(), Ok("<b>Hi</b>"));
+    }
+}
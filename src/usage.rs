@@ -0,0 +1,76 @@
+//! How often each `[hello]` variant is rendered
+//!
+//! Renders are counted by the shortcode's normalized attributes, so
+//! `[hello site="true" format='text']` and `[hello format=text site=true]`
+//! count as the same variant. The counts are persisted on shutdown and
+//! picked up again on startup.
+
+use crate::attrs::parse_shortcode_attrs;
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Render counts by attribute signature, shared with the `[hello]` filter
+#[derive(Debug, Clone, Default)]
+pub(crate) struct UsageCounter(Arc<RwLock<HashMap<String, u64>>>);
+
+impl UsageCounter {
+    /// Count a render of the shortcode with attributes `attrs`
+    pub fn record(&self, attrs: &str) {
+        *self.0.write().entry(signature(attrs)).or_insert(0) += 1;
+    }
+
+    /// The `n` most rendered signatures with their counts, most rendered
+    /// first and ties in signature order
+    pub fn top(&self, n: usize) -> Vec<(String, u64)> {
+        let mut counts: Vec<(String, u64)> = self
+            .0
+            .read()
+            .iter()
+            .map(|(signature, count)| (signature.clone(), *count))
+            .collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        counts.truncate(n);
+        counts
+    }
+
+    /// The counts as JSON, for persisting
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&*self.0.read()).unwrap_or_default()
+    }
+
+    /// Add counts previously persisted with [`to_json`](Self::to_json)
+    pub fn merge_json(&self, json: &str) -> serde_json::Result<()> {
+        let stored: HashMap<String, u64> = serde_json::from_str(json)?;
+        let mut counts = self.0.write();
+        for (signature, count) in stored {
+            *counts.entry(signature).or_insert(0) += count;
+        }
+        Ok(())
+    }
+}
+
+/// `attrs` as `key="value"` pairs sorted by key, e.g. `format="text" site="true"`
+fn signature(attrs: &str) -> String {
+    let mut attrs: Vec<(String, String)> = parse_shortcode_attrs(attrs).into_iter().collect();
+    attrs.sort();
+    attrs
+        .iter()
+        .map(|(key, value)| format!("{}={:?}", key, value))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signature_is_normalized() {
+        assert_eq!(
+            signature(r#"site="true" format='text'"#),
+            signature("format=text   site=true")
+        );
+        assert_eq!(signature(""), "");
+    }
+}
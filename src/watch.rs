@@ -0,0 +1,66 @@
+//! Config file watching
+//!
+//! The file's directory is watched rather than the file itself, so the
+//! watch survives editors that save by writing a new file and renaming it
+//! over the old one. Bursts of events are debounced into one reload.
+
+use notify::{RecommendedWatcher, RecursiveMode};
+use notify_debouncer_mini::{new_debouncer, DebounceEventResult, Debouncer};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Calls back when the watched file changes; dropping it stops the watch
+pub(crate) struct ConfigWatcher {
+    _debouncer: Debouncer<RecommendedWatcher>,
+}
+
+impl ConfigWatcher {
+    /// Call `on_change` each time `path` has changed and then been quiet for
+    /// `debounce`
+    pub fn start(
+        path: &Path,
+        debounce: Duration,
+        on_change: impl Fn() + Send + 'static,
+    ) -> notify::Result<Self> {
+        let file = absolute(path);
+        let dir = file
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| PathBuf::from("."));
+
+        let watched = file.clone();
+        let mut debouncer = new_debouncer(debounce, move |result: DebounceEventResult| {
+            if let Ok(events) = result {
+                if events.iter().any(|event| absolute(&event.path) == watched) {
+                    on_change();
+                }
+            }
+        })?;
+        debouncer
+            .watcher()
+            .watch(&dir, RecursiveMode::NonRecursive)?;
+
+        Ok(Self {
+            _debouncer: debouncer,
+        })
+    }
+}
+
+/// `path` made absolute and with symlinks resolved where possible, so event
+/// paths compare equal to the configured one
+fn absolute(path: &Path) -> PathBuf {
+    if let Ok(path) = path.canonicalize() {
+        return path;
+    }
+    // A file that was just renamed away can't be canonicalized, but its
+    // directory can
+    match (path.parent(), path.file_name()) {
+        (Some(dir), Some(name)) if !dir.as_os_str().is_empty() => dir
+            .canonicalize()
+            .map(|dir| dir.join(name))
+            .unwrap_or_else(|_| path.to_path_buf()),
+        _ => std::env::current_dir()
+            .map(|dir| dir.join(path))
+            .unwrap_or_else(|_| path.to_path_buf()),
+    }
+}
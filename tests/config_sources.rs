@@ -0,0 +1,26 @@
+//! Environment overrides are process-wide, so this lives in its own test
+//! binary where setting one can't race the unit tests that load settings.
+
+use hello_world_plugin::{HelloWorldPlugin, HelloWorldSettings};
+use std::collections::HashMap;
+
+#[test]
+fn test_config_sources_name_env_overrides() {
+    std::env::set_var("HELLO_WORLD_LOAD_MAX_ATTEMPTS", "5");
+
+    let plugin = HelloWorldPlugin::new();
+    plugin
+        .update_settings(HelloWorldSettings {
+            greeting_text: "Stored greeting".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+    let effective = plugin.effective_settings();
+    let sources: HashMap<String, String> = plugin.config_sources().into_iter().collect();
+
+    assert_eq!(effective.load_max_attempts, 5);
+    assert_eq!(effective.greeting_text, "Stored greeting");
+    assert_eq!(sources["load_max_attempts"], "env");
+    assert_eq!(sources["greeting_text"], "db");
+    assert_eq!(sources["responsive"], "default");
+}
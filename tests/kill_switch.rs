@@ -0,0 +1,25 @@
+//! `HELLO_WORLD_DISABLED` is process-wide, so this lives in its own test
+//! binary where it can't leak into the unit tests' activations.
+
+use hello_world_plugin::{HelloWorldPlugin, DISABLED_ENV};
+use parking_lot::RwLock;
+use rustpress_core::context::AppContext;
+use rustpress_core::hook::HookRegistry;
+use rustpress_core::plugin::{Plugin, PluginState};
+use std::sync::Arc;
+
+#[tokio::test]
+async fn test_disabled_env_skips_activation() {
+    std::env::set_var(DISABLED_ENV, "1");
+
+    let plugin = HelloWorldPlugin::new();
+    let registry = Arc::new(RwLock::new(HookRegistry::new()));
+    let mut ctx = AppContext::new();
+    ctx.insert(Arc::clone(&registry));
+
+    plugin.activate(&ctx).await.unwrap();
+
+    assert!(matches!(plugin.state(), PluginState::Inactive));
+    assert!(!plugin.is_hook_registered("shortcode_hello"));
+    assert!(!registry.read().has_filter("shortcode_hello"));
+}